@@ -0,0 +1,98 @@
+//! 房管操作，当前账号需要是直播间的房管或主播，否则接口会返回权限错误的 `code`
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult, RoomId};
+
+impl APIClient {
+    /// 禁言用户
+    ///
+    /// `mute_type` 原样作为 `type` 参数传给接口，`hour` 为禁言时长，0 为本场直播，-1 为永久
+    pub async fn add_silent_user(
+        &self,
+        room_id: impl Into<RoomId>,
+        tuid: u64,
+        mute_type: u32,
+        hour: i32,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?.to_string();
+        let tuid = tuid.to_string();
+        let mute_type = mute_type.to_string();
+        let hour = hour.to_string();
+        let param = [
+            ("room_id", room_id.as_str()),
+            ("tuid", tuid.as_str()),
+            ("mobile_app", "web"),
+            ("type", mute_type.as_str()),
+            ("msg", ""),
+            ("hour", hour.as_str()),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        self.admin_post(
+            "https://api.live.bilibili.com/xlive/web-ui/v1/banned/AddSilentUser",
+            &param,
+        )
+        .await
+    }
+
+    /// 把用户加入直播间黑名单，`hour` 为时长
+    pub async fn add_block_user(
+        &self,
+        room_id: impl Into<RoomId>,
+        block_uid: u64,
+        hour: i32,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?.to_string();
+        let block_uid = block_uid.to_string();
+        let hour = hour.to_string();
+        let param = [
+            ("roomid", room_id.as_str()),
+            ("block_uid", block_uid.as_str()),
+            ("hour", hour.as_str()),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        self.admin_post(
+            "https://api.live.bilibili.com/banned_service/v2/Silent/add_block_user",
+            &param,
+        )
+        .await
+    }
+
+    /// 移出直播间黑名单，`block_id` 为黑名单记录的 id，不是用户 uid
+    pub async fn del_block_user(
+        &self,
+        room_id: impl Into<RoomId>,
+        block_id: u64,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?.to_string();
+        let block_id = block_id.to_string();
+        let param = [
+            ("roomid", room_id.as_str()),
+            ("id", block_id.as_str()),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        self.admin_post(
+            "https://api.live.bilibili.com/banned_service/v1/Silent/del_room_block_user",
+            &param,
+        )
+        .await
+    }
+
+    async fn admin_post(
+        &self,
+        url: &str,
+        param: &[(&str, &str)],
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let resp = self
+            .client
+            .post(url)
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .form(param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+}
@@ -0,0 +1,108 @@
+//! 主播端操作，当前账号需要是直播间的主播
+
+use serde::{Deserialize, Serialize};
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult, RoomId};
+
+/// 推流地址，OBS 里 `addr` 填服务器，`code` 填推流码
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct RtmpInfo {
+    #[serde(default)]
+    pub addr: String,
+    #[serde(default)]
+    pub code: String,
+}
+
+impl RtmpInfo {
+    /// 完整的推流地址，可以直接交给 ffmpeg
+    pub fn url(&self) -> String {
+        format!("{}{}", self.addr, self.code)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct StartLiveInfo {
+    /// 是否改变了直播状态，重复开播时为 0
+    #[serde(default)]
+    pub change: u8,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub rtmp: RtmpInfo,
+}
+
+impl APIClient {
+    /// 开播，`area_v2` 为子分区 id
+    pub async fn start_live(
+        &self,
+        room_id: impl Into<RoomId>,
+        area_v2: u64,
+    ) -> Result<APIResult<StartLiveInfo>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?.to_string();
+        let area_v2 = area_v2.to_string();
+        let param = [
+            ("room_id", room_id.as_str()),
+            ("area_v2", area_v2.as_str()),
+            ("platform", "pc"),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let resp = self
+            .client
+            .post("https://api.live.bilibili.com/room/v1/Room/startLive")
+            .headers(self.default_headers("https://link.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<StartLiveInfo>>().await
+    }
+
+    /// 下播
+    pub async fn stop_live(
+        &self,
+        room_id: impl Into<RoomId>,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?.to_string();
+        let param = [
+            ("room_id", room_id.as_str()),
+            ("platform", "pc"),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let resp = self
+            .client
+            .post("https://api.live.bilibili.com/room/v1/Room/stopLive")
+            .headers(self.default_headers("https://link.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+
+    /// 修改直播间标题
+    pub async fn update_room_title(
+        &self,
+        room_id: impl Into<RoomId>,
+        title: &str,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?.to_string();
+        let param = [
+            ("room_id", room_id.as_str()),
+            ("title", title),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let resp = self
+            .client
+            .post("https://api.live.bilibili.com/room/v1/Room/update")
+            .headers(self.default_headers("https://link.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+}
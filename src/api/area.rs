@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult};
+
+/// 分区的 id 有时是数字有时是字符串
+fn u64_or_string<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let v = serde_json::Value::deserialize(deserializer)?;
+    Ok(v.as_u64()
+        .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+        .unwrap_or_default())
+}
+
+/// 父分区，比如 "网游"、"娱乐"
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct ParentArea {
+    #[serde(default)]
+    #[serde(deserialize_with = "u64_or_string")]
+    pub id: u64,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    #[serde(alias = "list")]
+    pub areas: Vec<SubArea>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct SubArea {
+    #[serde(default)]
+    #[serde(deserialize_with = "u64_or_string")]
+    pub id: u64,
+    #[serde(default)]
+    #[serde(deserialize_with = "u64_or_string")]
+    pub parent_id: u64,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub pic: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct AreaRoomList {
+    /// 分区里正在直播的直播间总数
+    #[serde(default)]
+    pub count: u64,
+    #[serde(default)]
+    pub list: Vec<AreaRoom>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct AreaRoom {
+    #[serde(default)]
+    pub roomid: u64,
+    #[serde(default)]
+    pub uid: u64,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub uname: String,
+    /// 人气值
+    #[serde(default)]
+    pub online: u64,
+    #[serde(default)]
+    pub cover: String,
+    #[serde(default)]
+    pub area_name: String,
+}
+
+impl APIClient {
+    /// 获取全部直播分区
+    pub async fn get_area_list(&self) -> Result<APIResult<Vec<ParentArea>>, reqwest::Error> {
+        let resp = self
+            .client
+            .get("https://api.live.bilibili.com/room/v1/Area/getList")
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<Vec<ParentArea>>>().await
+    }
+
+    /// 获取分区里正在直播的直播间，按人气排序，`area_id` 为 0 时是整个父分区，`page` 从 1 开始
+    pub async fn get_rooms_by_area(
+        &self,
+        parent_id: u64,
+        area_id: u64,
+        page: u32,
+    ) -> Result<APIResult<AreaRoomList>, reqwest::Error> {
+        let resp = self
+            .client
+            .get(format!(
+                "https://api.live.bilibili.com/room/v3/area/getRoomList?parent_area_id={parent_id}&area_id={area_id}&page={page}&page_size=30&sort_type=online"
+            ))
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<AreaRoomList>>().await
+    }
+}
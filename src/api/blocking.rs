@@ -0,0 +1,97 @@
+//! 同步接口，内部使用一个单线程的 tokio 运行时执行异步请求
+//!
+//! 不能在异步上下文里调用，否则会 panic，websocket 部分只有异步接口。
+//!
+//! ```no_run
+//! # use bilili_rs::api::blocking;
+//! let login_url = blocking::get_login_url().unwrap().data.unwrap();
+//! println!("{}", login_url.url);
+//! let client = blocking::poll_tokens(&login_url).unwrap().data.unwrap();
+//! client.send_barrage(1234u64, "hello").unwrap();
+//! ```
+
+use std::future::Future;
+
+use tokio::runtime::Runtime;
+
+use super::{
+    APIClient, APIResult, BarrageResult, LoginError, LoginUrl, NavInfo, RoomId, RoomPlayInfo,
+    SendBarrageError, UserInfo,
+};
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("build tokio runtime")
+}
+
+pub struct BlockingClient {
+    inner: APIClient,
+    rt: Runtime,
+}
+
+impl APIClient {
+    /// 转换成同步接口
+    pub fn blocking(self) -> BlockingClient {
+        BlockingClient {
+            inner: self,
+            rt: runtime(),
+        }
+    }
+}
+
+impl BlockingClient {
+    pub fn inner(&self) -> &APIClient {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> APIClient {
+        self.inner
+    }
+
+    /// 同步执行任意异步接口，如 `client.block_on(client.inner().get_wallet())`
+    pub fn block_on<F: Future>(&self, f: F) -> F::Output {
+        self.rt.block_on(f)
+    }
+
+    pub fn send_barrage(
+        &self,
+        room_id: impl Into<RoomId>,
+        barrage: &str,
+    ) -> Result<APIResult<BarrageResult>, SendBarrageError> {
+        self.block_on(self.inner.send_barrage(room_id, barrage))
+    }
+
+    pub fn get_room_play_info(
+        &self,
+        room_id: impl Into<RoomId>,
+    ) -> Result<APIResult<RoomPlayInfo>, reqwest::Error> {
+        self.block_on(self.inner.get_room_play_info(room_id))
+    }
+
+    pub fn get_self_info(&self) -> Result<APIResult<NavInfo>, reqwest::Error> {
+        self.block_on(self.inner.get_self_info())
+    }
+
+    pub fn get_user_info(&self, mid: u64) -> Result<APIResult<UserInfo>, reqwest::Error> {
+        self.block_on(self.inner.get_user_info(mid))
+    }
+}
+
+pub fn get_login_url() -> Result<APIResult<LoginUrl>, reqwest::Error> {
+    runtime().block_on(LoginUrl::get_login_url())
+}
+
+/// 同 `LoginUrl::poll_tokens`，登录成功后返回的客户端继续使用轮询时的运行时
+pub fn poll_tokens(login_url: &LoginUrl) -> Result<APIResult<BlockingClient>, LoginError> {
+    let rt = runtime();
+    let r = rt.block_on(login_url.poll_tokens())?;
+    Ok(APIResult {
+        code: r.code,
+        message: r.message,
+        ttl: r.ttl,
+        ts: r.ts,
+        data: r.data.map(|inner| BlockingClient { inner, rt }),
+    })
+}
@@ -0,0 +1,112 @@
+use reqwest::cookie::CookieStore;
+use serde::{Deserialize, Serialize};
+
+use tokio::time::{Duration, Instant};
+
+use super::retry::SendRetry;
+use super::{merge_cookie_list, APIClient, APIResult, BILI_URL};
+
+const COOKIE_BUVID3: &str = "buvid3=";
+/// 获取失败后多久再自动重试，见 `ensure_risk_cookies`
+const BUVID_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+pub(crate) struct BuvidState {
+    /// 获取到的 `buvid3`/`buvid4`，保存 session 时合并进 `cookies`
+    pub(crate) cookies: Vec<String>,
+    /// 上一次获取失败时，这个时间之前 `ensure_risk_cookies` 不再获取
+    retry_at: Option<Instant>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+struct FingerSpi {
+    #[serde(default)]
+    b_3: String,
+    #[serde(default)]
+    b_4: String,
+}
+
+impl APIClient {
+    /// cookie 里是否已经有 `buvid3`
+    pub fn has_buvid(&self) -> bool {
+        let domain_url = BILI_URL.parse().unwrap();
+        self.jar.cookies(&domain_url).is_some_and(|cookies| {
+            cookies
+                .to_str()
+                .is_ok_and(|c| c.split(';').any(|c| c.trim().starts_with(COOKIE_BUVID3)))
+        })
+    }
+
+    /// 确保 cookie 里有 `buvid3`/`buvid4`，没有时从 `/x/frontend/finger/spi` 获取
+    ///
+    /// 扫码登录不一定会下发这两个 cookie，缺少它们时发弹幕、送礼等接口
+    /// 容易被风控拦截（`code: -352`），这些接口会自动调用一次。
+    /// 获取到的 cookie 写入 `jar`，`save_session` 时也会保存下来。
+    pub async fn ensure_buvid(&self) -> Result<(), reqwest::Error> {
+        if self.has_buvid() {
+            return Ok(());
+        }
+
+        let r = async {
+            self.client
+                .get("https://api.bilibili.com/x/frontend/finger/spi")
+                .headers(self.default_headers("https://www.bilibili.com"))
+                .send_retry(self)
+                .await?
+                .json::<APIResult<FingerSpi>>()
+                .await
+        }
+        .await;
+        let r = match r {
+            Ok(r) => r,
+            Err(e) => {
+                self.buvid.lock().unwrap().retry_at = Some(Instant::now() + BUVID_RETRY_BACKOFF);
+                return Err(e);
+            }
+        };
+
+        match r.data {
+            Some(spi) if r.code == 0 => {
+                let cookies: Vec<String> = [("buvid3", spi.b_3), ("buvid4", spi.b_4)]
+                    .into_iter()
+                    .filter(|(_, value)| !value.is_empty())
+                    .map(|(name, value)| {
+                        format!("{}={}; Domain=.bilibili.com; Path=/", name, value)
+                    })
+                    .collect();
+                let domain_url = BILI_URL.parse().unwrap();
+                for cookie in &cookies {
+                    self.jar.add_cookie_str(cookie, &domain_url);
+                }
+                let mut state = self.buvid.lock().unwrap();
+                merge_cookie_list(&mut state.cookies, &cookies);
+                state.retry_at = None;
+            }
+            _ => {
+                log::warn!("get buvid error code: {}, message: {:?}", r.code, r.message);
+                self.buvid.lock().unwrap().retry_at = Some(Instant::now() + BUVID_RETRY_BACKOFF);
+            }
+        }
+        Ok(())
+    }
+
+    /// 发弹幕、送礼之类容易被风控的请求之前调用，补上 `buvid3` 和 `bili_ticket`
+    ///
+    /// 失败只记录日志，请求照常发出
+    pub(crate) async fn ensure_risk_cookies(&self) {
+        let backoff = self
+            .buvid
+            .lock()
+            .unwrap()
+            .retry_at
+            .is_some_and(|at| Instant::now() < at);
+        if !backoff {
+            if let Err(e) = self.ensure_buvid().await {
+                log::warn!("ensure_buvid error: {}", e);
+            }
+        }
+        if let Err(e) = self.ensure_bili_ticket().await {
+            log::warn!("refresh bili_ticket error: {}", e);
+        }
+    }
+}
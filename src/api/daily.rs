@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use tokio::time::{Duration, Instant};
+
+use super::{APIClient, ApiError, SendBarrageError};
+
+/// 每日任务的配置，数量为 0 或者 `None` 的任务会被跳过
+#[derive(Debug, Clone)]
+pub struct DailyTaskConfig {
+    /// 观看时长
+    pub watch: Duration,
+    /// 分享次数
+    pub share_count: u32,
+    /// 点赞次数
+    pub like_count: u32,
+    /// 发送的弹幕
+    pub barrage: Option<String>,
+    /// 粉丝勋章打卡，见 `APIClient::medal_signin`
+    pub medal_signin: bool,
+    /// 两个任务之间的间隔
+    pub interval: Duration,
+}
+
+impl Default for DailyTaskConfig {
+    fn default() -> Self {
+        Self {
+            watch: Duration::from_secs(5 * 60),
+            share_count: 5,
+            like_count: 3,
+            barrage: None,
+            medal_signin: true,
+            interval: Duration::from_secs(3),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DailyTaskError {
+    #[error("ApiError {0}")]
+    ApiError(#[from] ApiError),
+    #[error("SendBarrageError {0}")]
+    SendBarrageError(#[from] SendBarrageError),
+    #[error("HttpError {0}")]
+    HttpError(#[from] reqwest::Error),
+}
+
+impl DailyTaskError {
+    fn is_not_login(&self) -> bool {
+        matches!(self, DailyTaskError::ApiError(ApiError::NotLogin { .. }))
+    }
+}
+
+/// 下一个任务开始前的等待时间，被风控时按 `ApiError::retry_after` 多等一会
+fn pause_after<T>(r: &Result<T, DailyTaskError>, interval: Duration) -> Duration {
+    match r {
+        Err(DailyTaskError::ApiError(e)) => e.retry_after().map_or(interval, |d| d.max(interval)),
+        _ => interval,
+    }
+}
+
+/// 每个任务的结果，`None` 表示跳过或者因为登录失效没有执行
+#[derive(Debug, Default)]
+pub struct DailyTaskReport {
+    pub watch: Option<Result<(), DailyTaskError>>,
+    /// 全部成功时为分享次数
+    pub share: Option<Result<u32, DailyTaskError>>,
+    pub like: Option<Result<(), DailyTaskError>>,
+    pub barrage: Option<Result<(), DailyTaskError>>,
+    /// 获得的亲密度，见 `MedalSignin::intimacy`
+    pub medal_signin: Option<Result<Option<u64>, DailyTaskError>>,
+}
+
+/// 依次完成观看、分享、点赞、发弹幕、勋章打卡这几个每日任务，`anchor_id` 为主播的 uid
+///
+/// 开始前检查登录状态，未登录时返回 `ApiError::NotLogin`；
+/// 执行中某个任务返回未登录时，后面的任务不再执行。
+pub async fn run_daily_tasks(
+    client: Arc<APIClient>,
+    room_id: u64,
+    anchor_id: u64,
+    config: DailyTaskConfig,
+) -> Result<DailyTaskReport, DailyTaskError> {
+    if !client.check_login().await? {
+        return Err(ApiError::NotLogin {
+            message: "账号未登录".to_string(),
+        }
+        .into());
+    }
+
+    let mut report = DailyTaskReport::default();
+
+    if !config.watch.is_zero() {
+        let r = watch(&client, room_id, config.watch).await;
+        let stop = r.as_ref().is_err_and(|e| e.is_not_login());
+        let pause = pause_after(&r, config.interval);
+        report.watch = Some(r);
+        if stop {
+            return Ok(report);
+        }
+        tokio::time::sleep(pause).await;
+    }
+
+    if config.share_count > 0 {
+        let r = share(&client, room_id, config.share_count).await;
+        let stop = r.as_ref().is_err_and(|e| e.is_not_login());
+        let pause = pause_after(&r, config.interval);
+        report.share = Some(r);
+        if stop {
+            return Ok(report);
+        }
+        tokio::time::sleep(pause).await;
+    }
+
+    if config.like_count > 0 {
+        let r = like(&client, room_id, anchor_id, config.like_count).await;
+        let stop = r.as_ref().is_err_and(|e| e.is_not_login());
+        let pause = pause_after(&r, config.interval);
+        report.like = Some(r);
+        if stop {
+            return Ok(report);
+        }
+        tokio::time::sleep(pause).await;
+    }
+
+    if let Some(barrage) = &config.barrage {
+        let r = client
+            .send_barrage(room_id, barrage)
+            .await
+            .map_err(DailyTaskError::from)
+            .and_then(|r| Ok(r.check().map(|_| ())?));
+        let stop = r.as_ref().is_err_and(|e| e.is_not_login());
+        let pause = pause_after(&r, config.interval);
+        report.barrage = Some(r);
+        if stop {
+            return Ok(report);
+        }
+        tokio::time::sleep(pause).await;
+    }
+
+    if config.medal_signin {
+        let r = client
+            .medal_signin(room_id)
+            .await
+            .map_err(DailyTaskError::from)
+            .and_then(|r| {
+                r.barrage.check()?;
+                Ok(r.intimacy)
+            });
+        report.medal_signin = Some(r);
+    }
+
+    Ok(report)
+}
+
+async fn watch(client: &APIClient, room_id: u64, duration: Duration) -> Result<(), DailyTaskError> {
+    client.enter_room(room_id).await?.check()?;
+    let deadline = Instant::now() + duration;
+    let mut interval = 60;
+    while Instant::now() < deadline {
+        let remain = deadline - Instant::now();
+        tokio::time::sleep(remain.min(Duration::from_secs(interval))).await;
+        if let Some(hb) = client.web_heartbeat(room_id, interval).await?.check()? {
+            if hb.next_interval > 0 {
+                interval = hb.next_interval;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn share(client: &APIClient, room_id: u64, count: u32) -> Result<u32, DailyTaskError> {
+    let r = client.share_room_times(room_id, count).await?;
+    match r.error {
+        Some(e) => Err(e.into()),
+        None => Ok(r.succeeded),
+    }
+}
+
+async fn like(
+    client: &APIClient,
+    room_id: u64,
+    anchor_id: u64,
+    count: u32,
+) -> Result<(), DailyTaskError> {
+    for i in 0..count {
+        if i > 0 {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        client
+            .like_report_v3(room_id, anchor_id, 1)
+            .await?
+            .check()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use tokio::time::Duration;
+
+use super::APIResult;
+
+/// 接口返回的 `code` 不为 0 时的错误
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    /// -101 账号未登录
+    #[error("NotLogin: {message}")]
+    NotLogin { message: String },
+    /// -111 csrf 校验失败
+    #[error("CsrfFailed: {message}")]
+    CsrfFailed { message: String },
+    /// 请求被风控拦截，`retry_after` 为建议的等待时间，见 `ApiError::new`
+    #[error("RiskControl code: {code}, message: {message}")]
+    RiskControl {
+        code: i32,
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    /// `code` 为 0 但没有 `data`
+    #[error("EmptyData")]
+    EmptyData,
+    #[error("ApiError code: {code}, message: {message}")]
+    Other { code: i32, message: String },
+}
+
+impl ApiError {
+    /// 风控相关的 `code`：
+    ///
+    /// - -352 风控校验失败，通常是缺少 `buvid3` 或 `bili_ticket`，等待没有用，
+    ///   先调用 `ensure_buvid` / `ensure_bili_ticket`
+    /// - -412 请求被拦截，一般是 ip 被限制，等待 10 分钟
+    /// - -509 请求过于频繁，等待 10 秒
+    /// - 1003 直播接口的风控拦截，等待 1 分钟
+    /// - 10030 弹幕发送频率过快，等待 5 秒
+    pub fn new(code: i32, message: String) -> Self {
+        let retry_after = |secs| Some(Duration::from_secs(secs));
+        match code {
+            -101 => ApiError::NotLogin { message },
+            -111 => ApiError::CsrfFailed { message },
+            -352 => ApiError::RiskControl {
+                code,
+                message,
+                retry_after: None,
+            },
+            -412 => ApiError::RiskControl {
+                code,
+                message,
+                retry_after: retry_after(10 * 60),
+            },
+            -509 => ApiError::RiskControl {
+                code,
+                message,
+                retry_after: retry_after(10),
+            },
+            1003 => ApiError::RiskControl {
+                code,
+                message,
+                retry_after: retry_after(60),
+            },
+            10030 => ApiError::RiskControl {
+                code,
+                message,
+                retry_after: retry_after(5),
+            },
+            _ => ApiError::Other { code, message },
+        }
+    }
+
+    /// 被风控时建议的等待时间
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ApiError::RiskControl { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> i32 {
+        match self {
+            ApiError::NotLogin { .. } => -101,
+            ApiError::CsrfFailed { .. } => -111,
+            ApiError::RiskControl { code, .. } | ApiError::Other { code, .. } => *code,
+            ApiError::EmptyData => 0,
+        }
+    }
+}
+
+impl<T> APIResult<T> {
+    /// `code` 不为 0 或者没有 `data` 时返回 `Err`
+    ///
+    /// ```no_run
+    /// # use bilili_rs::api::{APIClient, ApiError};
+    /// # async fn run(api_client: APIClient) -> Result<(), Box<dyn std::error::Error>> {
+    /// let info = api_client.get_self_info().await?.into_result()?;
+    /// println!("{}", info.uname);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_result(self) -> Result<T, ApiError> {
+        self.check()?.ok_or(ApiError::EmptyData)
+    }
+
+    /// 只检查 `code`，用于成功时 `data` 可能为 `null` 的接口
+    pub fn check(self) -> Result<Option<T>, ApiError> {
+        if self.code == 0 {
+            Ok(self.data)
+        } else {
+            Err(ApiError::new(self.code, self.message.unwrap_or_default()))
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult, RoomId};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GiftInfo {
+    pub id: u64,
+    #[serde(default)]
+    pub name: String,
+    /// 金瓜子为 1/1000 元，银瓜子无价值
+    #[serde(default)]
+    pub price: u64,
+    /// gold / silver
+    #[serde(default)]
+    pub coin_type: String,
+    #[serde(default)]
+    pub img_basic: String,
+}
+
+/// 直播间礼物列表，key 为礼物 id
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(from = "RawGiftConfig")]
+pub struct GiftConfig {
+    pub gifts: HashMap<u64, GiftInfo>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawGiftConfig {
+    #[serde(default)]
+    list: Vec<GiftInfo>,
+}
+
+impl From<RawGiftConfig> for GiftConfig {
+    fn from(raw: RawGiftConfig) -> Self {
+        GiftConfig {
+            gifts: raw.list.into_iter().map(|g| (g.id, g)).collect(),
+        }
+    }
+}
+
+impl GiftConfig {
+    pub fn get(&self, gift_id: u64) -> Option<&GiftInfo> {
+        self.gifts.get(&gift_id)
+    }
+}
+
+impl APIClient {
+    /// 获取直播间可送的礼物列表
+    pub async fn get_gift_config(
+        &self,
+        room_id: impl Into<RoomId>,
+        area_parent_id: u64,
+        area_id: u64,
+    ) -> Result<APIResult<GiftConfig>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?;
+        let resp = self
+            .client
+            .get(format!(
+                "https://api.live.bilibili.com/xlive/web-room/v1/giftPanel/giftConfig?platform=pc&room_id={room_id}&area_parent_id={area_parent_id}&area_id={area_id}"
+            ))
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<GiftConfig>>().await
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CoinType {
+    /// 金瓜子(电池)
+    Gold,
+    /// 银瓜子，人气票之类的免费礼物使用
+    #[default]
+    Silver,
+}
+
+impl CoinType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoinType::Gold => "gold",
+            CoinType::Silver => "silver",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SendGift {
+    pub gift_id: u64,
+    pub gift_num: u32,
+    /// 单价，见 `GiftInfo::price`
+    pub price: u64,
+    pub coin_type: CoinType,
+    /// 从包裹里送出时需要填写包裹 id
+    pub bag_id: Option<u64>,
+}
+
+impl SendGift {
+    pub fn from_info(info: &GiftInfo, gift_num: u32) -> Self {
+        let coin_type = if info.coin_type == CoinType::Gold.as_str() {
+            CoinType::Gold
+        } else {
+            CoinType::Silver
+        };
+        SendGift {
+            gift_id: info.id,
+            gift_num,
+            price: info.price,
+            coin_type,
+            bag_id: None,
+        }
+    }
+}
+
+impl APIClient {
+    /// 送礼物，`ruid` 为主播的 uid
+    pub async fn send_gift(
+        &self,
+        room_id: impl Into<RoomId>,
+        ruid: u64,
+        gift: &SendGift,
+    ) -> Result<APIResult<GiftSendResult>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?;
+        self.ensure_risk_cookies().await;
+        let url = match (gift.bag_id, gift.coin_type) {
+            (Some(_), _) => "https://api.live.bilibili.com/xlive/revenue/v1/gift/sendBag",
+            (None, CoinType::Gold) => {
+                "https://api.live.bilibili.com/xlive/revenue/v1/gift/sendGold"
+            }
+            (None, CoinType::Silver) => {
+                "https://api.live.bilibili.com/xlive/revenue/v1/gift/sendSilver"
+            }
+        };
+
+        let room_id = room_id.to_string();
+        let ruid = ruid.to_string();
+        let gift_id = gift.gift_id.to_string();
+        let gift_num = gift.gift_num.to_string();
+        let price = gift.price.to_string();
+        let bag_id = gift.bag_id.unwrap_or_default().to_string();
+        let param = [
+            ("uid", self.token.uid.as_str()),
+            ("gift_id", gift_id.as_str()),
+            ("ruid", ruid.as_str()),
+            ("send_ruid", "0"),
+            ("gift_num", gift_num.as_str()),
+            ("coin_type", gift.coin_type.as_str()),
+            ("bag_id", bag_id.as_str()),
+            ("platform", "pc"),
+            ("biz_code", "Live"),
+            ("biz_id", room_id.as_str()),
+            ("storm_beat_id", "0"),
+            ("price", price.as_str()),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let resp = self
+            .client
+            .post(url)
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<GiftSendResult>>().await
+    }
+
+    /// 查询钱包余额
+    pub async fn get_wallet(&self) -> Result<APIResult<Wallet>, reqwest::Error> {
+        let resp = self
+            .client
+            .get("https://api.live.bilibili.com/xlive/revenue/v1/wallet/getStatus")
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<Wallet>>().await
+    }
+
+    /// 同 `send_gift`，送出前先查询余额，不够时返回 `SendGiftError::InsufficientBalance`
+    ///
+    /// 从包裹送出的礼物不检查余额
+    pub async fn send_gift_checked(
+        &self,
+        room_id: impl Into<RoomId>,
+        ruid: u64,
+        gift: &SendGift,
+    ) -> Result<APIResult<GiftSendResult>, SendGiftError> {
+        let room_id = self.resolve_room_id(room_id).await?;
+        if gift.bag_id.is_none() {
+            let need = gift.price.saturating_mul(gift.gift_num as u64);
+            if let Some(wallet) = self.get_wallet().await?.data {
+                let have = match gift.coin_type {
+                    CoinType::Gold => wallet.gold,
+                    CoinType::Silver => wallet.silver,
+                };
+                if have < need {
+                    return Err(SendGiftError::InsufficientBalance {
+                        coin_type: gift.coin_type,
+                        need,
+                        have,
+                    });
+                }
+            }
+        }
+        Ok(self.send_gift(room_id, ruid, gift).await?)
+    }
+}
+
+/// 送礼的结果，不同礼物返回的 `data` 格式不一样，认不出来的字段在 `raw` 里
+///
+/// 序列化时输出原始的 `raw`，反序列化后得到同样的结果
+#[derive(Debug, Clone, Default)]
+pub struct GiftSendResult {
+    /// 订单号
+    pub tid: String,
+    /// 连击 id，同一个连击的礼物相同
+    pub combo_id: Option<String>,
+    /// 送的是盲盒时为开出的礼物
+    pub blind_gift: Option<BlindGift>,
+    pub raw: serde_json::Value,
+}
+
+/// 盲盒开出的礼物
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct BlindGift {
+    /// 盲盒本身
+    #[serde(default)]
+    pub original_gift_id: u64,
+    #[serde(default)]
+    pub original_gift_name: String,
+    /// 盲盒的价格，单位：金瓜子
+    #[serde(default)]
+    pub original_gift_price: u64,
+    /// 开出的礼物
+    #[serde(default)]
+    pub gift_id: u64,
+    #[serde(default)]
+    pub gift_name: String,
+    /// 开出的礼物价值，单位：金瓜子
+    #[serde(default)]
+    pub gift_tip_price: u64,
+    /// 如 "爆出"
+    #[serde(default)]
+    pub gift_action: String,
+}
+
+impl Serialize for GiftSendResult {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.raw.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GiftSendResult {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = serde_json::Value::deserialize(deserializer)?;
+        // 有的接口把礼物放在 gift_list 里
+        let gift = raw["gift_list"]
+            .as_array()
+            .and_then(|list| list.first())
+            .unwrap_or(&raw);
+        let string_of = |v: &serde_json::Value| match v {
+            serde_json::Value::String(s) if !s.is_empty() => Some(s.clone()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        };
+
+        let tid = string_of(&raw["tid"])
+            .or_else(|| string_of(&gift["tid"]))
+            .unwrap_or_default();
+        let combo_id = ["combo_id", "batch_combo_id"]
+            .iter()
+            .find_map(|key| string_of(&gift[key]).or_else(|| string_of(&raw[key])));
+        let blind_gift = [&gift["blind_gift"], &raw["blind_gift"]]
+            .into_iter()
+            .find(|v| v.is_object())
+            .and_then(|v| serde_json::from_value::<BlindGift>(v.clone()).ok())
+            .map(|mut blind| {
+                blind.gift_id = gift["gift_id"].as_u64().unwrap_or_default();
+                blind.gift_name = gift["gift_name"].as_str().unwrap_or_default().to_string();
+                blind
+            });
+
+        Ok(GiftSendResult {
+            tid,
+            combo_id,
+            blind_gift,
+            raw,
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Wallet {
+    /// 金瓜子，1000 金瓜子 = 1 元 = 10 电池
+    #[serde(default)]
+    pub gold: u64,
+    #[serde(default)]
+    pub silver: u64,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SendGiftError {
+    #[error("InsufficientBalance {coin_type:?} need: {need}, have: {have}")]
+    InsufficientBalance {
+        coin_type: CoinType,
+        need: u64,
+        have: u64,
+    },
+    #[error("HttpError {0}")]
+    HttpError(#[from] reqwest::Error),
+}
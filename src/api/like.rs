@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use tokio::{task::JoinHandle, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult, RoomId};
+
+impl APIClient {
+    /// 给直播间点赞，`anchor_id` 为主播的 uid，`click_time` 为这次上报的点赞次数
+    pub async fn like_report_v3(
+        &self,
+        room_id: impl Into<RoomId>,
+        anchor_id: u64,
+        click_time: u32,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?.to_string();
+        let anchor_id = anchor_id.to_string();
+        let click_time = click_time.to_string();
+        let param = [
+            ("click_time", click_time.as_str()),
+            ("room_id", room_id.as_str()),
+            ("uid", self.token.uid.as_str()),
+            ("anchor_id", anchor_id.as_str()),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let resp = self
+            .client
+            .post(
+                "https://api.live.bilibili.com/xlive/app-ucenter/v1/like_info_v3/like/likeReportV3",
+            )
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+}
+
+/// 每隔 `interval` 给直播间点一次赞，和网页端看直播时的行为一致
+///
+/// 单次上报失败只会打印日志，不会退出循环，调用 `cancel.cancel()` 后退出。
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::{sync::Arc, time::Duration};
+/// # use bilili_rs::api::{spawn_like_loop, APIClient};
+/// # async fn run(api_client: Arc<APIClient>) {
+/// let cancel = tokio_util::sync::CancellationToken::new();
+/// let handle = spawn_like_loop(api_client, 1234, 5678, Duration::from_secs(5), cancel.clone());
+/// /* ... */
+/// cancel.cancel();
+/// handle.await.unwrap();
+/// # }
+/// ```
+pub fn spawn_like_loop(
+    api_client: Arc<APIClient>,
+    room_id: u64,
+    anchor_id: u64,
+    interval: Duration,
+    cancel: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match api_client.like_report_v3(room_id, anchor_id, 1).await {
+                Ok(APIResult { code: 0, .. }) => log::debug!("[{room_id}] like report ok"),
+                Ok(r) => log::warn!("[{room_id}] like report code: {}, {:?}", r.code, r.message),
+                Err(e) => log::warn!("[{room_id}] like report error {}", e),
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = cancel.cancelled() => return,
+            }
+        }
+    })
+}
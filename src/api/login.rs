@@ -0,0 +1,439 @@
+use base64::Engine;
+use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Encrypt, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::{
+    collect_set_cookies, APIClient, APIClientBuilder, APIResult, CheckCookieError, UserToken,
+};
+
+/// 密码和短信登录请求的 `Referer`
+const PASSPORT_LOGIN_URL: &str = "https://passport.bilibili.com/login";
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct LoginKey {
+    hash: String,
+    key: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct Geetest {
+    gt: String,
+    challenge: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct CaptchaInfo {
+    token: String,
+    geetest: Geetest,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct LoginResult {
+    #[serde(default)]
+    status: i32,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    refresh_token: String,
+}
+
+/// 完成极验验证后得到的结果
+#[derive(Debug, Clone, Default)]
+pub struct CaptchaResult {
+    /// `CaptchaRequired` 里的 `token`
+    pub token: String,
+    pub challenge: String,
+    pub validate: String,
+    pub seccode: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PasswordLoginError {
+    /// 需要先完成极验验证，再调用 `PasswordLogin::login_with_captcha`
+    #[error("CaptchaRequired gt: {gt}, challenge: {challenge}")]
+    CaptchaRequired {
+        token: String,
+        gt: String,
+        challenge: String,
+    },
+    #[error("RsaError {0}")]
+    RsaError(String),
+    #[error("UnknownError code: {code}, message: {message}")]
+    UnknownError { code: i32, message: String },
+    #[error("CookieError {0}")]
+    CookieError(#[from] CheckCookieError),
+    #[error("HttpError {0}")]
+    HttpError(#[from] reqwest::Error),
+}
+
+/// 账号密码登录
+///
+/// web 端的密码登录几乎总是需要极验验证码，所以通常的流程是：
+///
+/// ```no_run
+/// # use bilili_rs::api::{CaptchaResult, PasswordLogin, PasswordLoginError};
+/// # async fn run() {
+/// match PasswordLogin::login("username", "password").await {
+///     Err(PasswordLoginError::CaptchaRequired { token, gt, challenge }) => {
+///         /* 用 gt 和 challenge 让用户完成极验验证，得到 validate 和 seccode */
+///         let captcha = CaptchaResult { token, challenge, ..Default::default() };
+///         let client = PasswordLogin::login_with_captcha("username", "password", &captcha)
+///             .await
+///             .unwrap();
+///     }
+///     r => { /* 没有要求验证码 */ }
+/// }
+/// # }
+/// ```
+pub struct PasswordLogin;
+
+impl PasswordLogin {
+    pub async fn login(
+        username: &str,
+        password: &str,
+    ) -> Result<APIResult<APIClient>, PasswordLoginError> {
+        Self::login_with(&APIClient::builder(), username, password, None).await
+    }
+
+    pub async fn login_with_captcha(
+        username: &str,
+        password: &str,
+        captcha: &CaptchaResult,
+    ) -> Result<APIResult<APIClient>, PasswordLoginError> {
+        Self::login_with(&APIClient::builder(), username, password, Some(captcha)).await
+    }
+
+    /// 登录成功后使用 `builder` 构建 `APIClient`，接口本身返回错误时 `data` 为 `None`
+    pub async fn login_with(
+        builder: &APIClientBuilder,
+        username: &str,
+        password: &str,
+        captcha: Option<&CaptchaResult>,
+    ) -> Result<APIResult<APIClient>, PasswordLoginError> {
+        let client = builder.build_http_client(Arc::new(reqwest::cookie::Jar::default()))?;
+
+        let r = client
+            .get("https://passport.bilibili.com/x/passport-login/web/key")
+            .headers(builder.default_headers(PASSPORT_LOGIN_URL))
+            .send()
+            .await?
+            .json::<APIResult<LoginKey>>()
+            .await?;
+        let key = match r.data {
+            Some(key) if r.code == 0 => key,
+            _ => {
+                return Err(PasswordLoginError::UnknownError {
+                    code: r.code,
+                    message: r.message.unwrap_or_default(),
+                })
+            }
+        };
+        let password = encrypt_password(&key, password)?;
+
+        let captcha = match captcha {
+            Some(captcha) => captcha.clone(),
+            None => CaptchaResult::default(),
+        };
+        let param = [
+            ("username", username),
+            ("password", password.as_str()),
+            ("keep", "0"),
+            ("token", captcha.token.as_str()),
+            ("challenge", captcha.challenge.as_str()),
+            ("validate", captcha.validate.as_str()),
+            ("seccode", captcha.seccode.as_str()),
+            ("source", "main_web"),
+            ("go_url", "https://www.bilibili.com"),
+        ];
+        let resp = client
+            .post("https://passport.bilibili.com/x/passport-login/web/login")
+            .headers(builder.default_headers(PASSPORT_LOGIN_URL))
+            .form(&param)
+            .send()
+            .await?;
+        let cookies = collect_set_cookies(&resp);
+        let APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data,
+        } = resp.json::<APIResult<LoginResult>>().await?;
+
+        match data {
+            Some(r) if code == 0 && r.status == 0 => {
+                let (token, jar) = UserToken::create_from_tokens(&cookies)?;
+                let mut api_client = builder.clone().build(token, jar, cookies)?;
+                api_client.refresh_token = r.refresh_token;
+                if let Err(e) = api_client.ensure_buvid().await {
+                    log::warn!("ensure_buvid error: {}", e);
+                }
+                Ok(APIResult {
+                    code,
+                    message,
+                    ttl,
+                    ts,
+                    data: Some(api_client),
+                })
+            }
+            // 需要手机号等额外验证
+            Some(r) if code == 0 => Err(PasswordLoginError::UnknownError {
+                code: r.status,
+                message: r.message,
+            }),
+            // -105: 验证码错误
+            _ if code == -105 => {
+                let r = fetch_captcha(builder, &client).await?;
+                match r.data {
+                    Some(CaptchaInfo { token, geetest }) if r.code == 0 => {
+                        Err(PasswordLoginError::CaptchaRequired {
+                            token,
+                            gt: geetest.gt,
+                            challenge: geetest.challenge,
+                        })
+                    }
+                    _ => Err(PasswordLoginError::UnknownError {
+                        code: r.code,
+                        message: r.message.unwrap_or_default(),
+                    }),
+                }
+            }
+            _ => Ok(APIResult {
+                code,
+                message,
+                ttl,
+                ts,
+                data: None,
+            }),
+        }
+    }
+}
+
+/// 短信验证码登录
+///
+/// 发送验证码前需要完成极验验证：
+///
+/// ```no_run
+/// # use bilili_rs::api::{CaptchaResult, SmsLogin, SmsLoginError};
+/// # async fn run() {
+/// let captcha = match SmsLogin::send_code("13800000000", 86).await {
+///     Err(SmsLoginError::CaptchaRequired { token, gt, challenge }) => {
+///         /* 用 gt 和 challenge 让用户完成极验验证，得到 validate 和 seccode */
+///         CaptchaResult { token, challenge, ..Default::default() }
+///     }
+///     r => panic!("{:?}", r),
+/// };
+/// let captcha_key = SmsLogin::send_code_with_captcha("13800000000", 86, &captcha)
+///     .await
+///     .unwrap();
+/// /* 用户收到短信验证码后 */
+/// let client = SmsLogin::login("13800000000", 86, "123456", &captcha_key)
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub struct SmsLogin;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct SmsSendResult {
+    #[serde(default)]
+    captcha_key: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SmsLoginError {
+    /// 需要先完成极验验证，再调用 `SmsLogin::send_code_with_captcha`
+    #[error("CaptchaRequired gt: {gt}, challenge: {challenge}")]
+    CaptchaRequired {
+        token: String,
+        gt: String,
+        challenge: String,
+    },
+    #[error("WrongCode")]
+    WrongCode,
+    #[error("CodeExpired")]
+    CodeExpired,
+    #[error("UnknownError code: {code}, message: {message}")]
+    UnknownError { code: i32, message: String },
+    #[error("CookieError {0}")]
+    CookieError(#[from] CheckCookieError),
+    #[error("HttpError {0}")]
+    HttpError(#[from] reqwest::Error),
+}
+
+impl SmsLogin {
+    /// 发送短信验证码，成功时返回登录要用的 `captcha_key`
+    ///
+    /// `cid` 是国际区号，中国大陆为 86
+    pub async fn send_code(phone: &str, cid: u32) -> Result<String, SmsLoginError> {
+        Self::send_code_with(&APIClient::builder(), phone, cid, None).await
+    }
+
+    pub async fn send_code_with_captcha(
+        phone: &str,
+        cid: u32,
+        captcha: &CaptchaResult,
+    ) -> Result<String, SmsLoginError> {
+        Self::send_code_with(&APIClient::builder(), phone, cid, Some(captcha)).await
+    }
+
+    pub async fn send_code_with(
+        builder: &APIClientBuilder,
+        phone: &str,
+        cid: u32,
+        captcha: Option<&CaptchaResult>,
+    ) -> Result<String, SmsLoginError> {
+        let client = builder.build_http_client(Arc::new(reqwest::cookie::Jar::default()))?;
+
+        let captcha = match captcha {
+            Some(captcha) => captcha.clone(),
+            None => CaptchaResult::default(),
+        };
+        let cid = cid.to_string();
+        let param = [
+            ("cid", cid.as_str()),
+            ("tel", phone),
+            ("source", "main_web"),
+            ("token", captcha.token.as_str()),
+            ("challenge", captcha.challenge.as_str()),
+            ("validate", captcha.validate.as_str()),
+            ("seccode", captcha.seccode.as_str()),
+        ];
+        let r = client
+            .post("https://passport.bilibili.com/x/passport-login/web/sms/send")
+            .headers(builder.default_headers(PASSPORT_LOGIN_URL))
+            .form(&param)
+            .send()
+            .await?
+            .json::<APIResult<SmsSendResult>>()
+            .await?;
+
+        match r.data {
+            Some(data) if r.code == 0 => Ok(data.captcha_key),
+            // -105: 验证码错误
+            _ if r.code == -105 => {
+                let r = fetch_captcha(builder, &client).await?;
+                match r.data {
+                    Some(CaptchaInfo { token, geetest }) if r.code == 0 => {
+                        Err(SmsLoginError::CaptchaRequired {
+                            token,
+                            gt: geetest.gt,
+                            challenge: geetest.challenge,
+                        })
+                    }
+                    _ => Err(SmsLoginError::UnknownError {
+                        code: r.code,
+                        message: r.message.unwrap_or_default(),
+                    }),
+                }
+            }
+            _ => Err(SmsLoginError::UnknownError {
+                code: r.code,
+                message: r.message.unwrap_or_default(),
+            }),
+        }
+    }
+
+    pub async fn login(
+        phone: &str,
+        cid: u32,
+        code: &str,
+        captcha_key: &str,
+    ) -> Result<APIResult<APIClient>, SmsLoginError> {
+        Self::login_with(&APIClient::builder(), phone, cid, code, captcha_key).await
+    }
+
+    /// 登录成功后使用 `builder` 构建 `APIClient`，接口本身返回错误时 `data` 为 `None`
+    pub async fn login_with(
+        builder: &APIClientBuilder,
+        phone: &str,
+        cid: u32,
+        code: &str,
+        captcha_key: &str,
+    ) -> Result<APIResult<APIClient>, SmsLoginError> {
+        let client = builder.build_http_client(Arc::new(reqwest::cookie::Jar::default()))?;
+
+        let cid = cid.to_string();
+        let param = [
+            ("cid", cid.as_str()),
+            ("tel", phone),
+            ("code", code),
+            ("source", "main_web"),
+            ("captcha_key", captcha_key),
+            ("keep", "0"),
+            ("go_url", "https://www.bilibili.com"),
+        ];
+        let resp = client
+            .post("https://passport.bilibili.com/x/passport-login/web/login/sms")
+            .headers(builder.default_headers(PASSPORT_LOGIN_URL))
+            .form(&param)
+            .send()
+            .await?;
+        let cookies = collect_set_cookies(&resp);
+        let APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data,
+        } = resp.json::<APIResult<LoginResult>>().await?;
+
+        match data {
+            Some(r) if code == 0 && r.status == 0 => {
+                let (token, jar) = UserToken::create_from_tokens(&cookies)?;
+                let mut api_client = builder.clone().build(token, jar, cookies)?;
+                api_client.refresh_token = r.refresh_token;
+                if let Err(e) = api_client.ensure_buvid().await {
+                    log::warn!("ensure_buvid error: {}", e);
+                }
+                Ok(APIResult {
+                    code,
+                    message,
+                    ttl,
+                    ts,
+                    data: Some(api_client),
+                })
+            }
+            Some(r) if code == 0 => Err(SmsLoginError::UnknownError {
+                code: r.status,
+                message: r.message,
+            }),
+            // 1006: 请输入正确的短信验证码
+            _ if code == 1006 => Err(SmsLoginError::WrongCode),
+            // 1007: 短信验证码已过期
+            _ if code == 1007 => Err(SmsLoginError::CodeExpired),
+            _ => Ok(APIResult {
+                code,
+                message,
+                ttl,
+                ts,
+                data: None,
+            }),
+        }
+    }
+}
+
+async fn fetch_captcha(
+    builder: &APIClientBuilder,
+    client: &reqwest::Client,
+) -> Result<APIResult<CaptchaInfo>, reqwest::Error> {
+    client
+        .get("https://passport.bilibili.com/x/passport-login/captcha?source=main_web")
+        .headers(builder.default_headers(PASSPORT_LOGIN_URL))
+        .send()
+        .await?
+        .json::<APIResult<CaptchaInfo>>()
+        .await
+}
+
+/// 用服务端给的公钥加密 `hash + password`
+fn encrypt_password(key: &LoginKey, password: &str) -> Result<String, PasswordLoginError> {
+    let public_key = RsaPublicKey::from_public_key_pem(&key.key)
+        .map_err(|e| PasswordLoginError::RsaError(e.to_string()))?;
+    let data = format!("{}{}", key.hash, password);
+    let encrypted = public_key
+        .encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, data.as_bytes())
+        .map_err(|e| PasswordLoginError::RsaError(e.to_string()))?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(encrypted))
+}
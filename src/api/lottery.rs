@@ -0,0 +1,80 @@
+//! 直播间抽奖，websocket 收到开始的消息后调用
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult, RoomId};
+
+impl APIClient {
+    /// 参与红包抽奖，`lot_id` 见 `POPULARITY_RED_POCKET_START` 消息
+    ///
+    /// 接口需要主播的 uid，这里通过 `room_init` 查询
+    pub async fn join_red_pocket(
+        &self,
+        room_id: impl Into<RoomId>,
+        lot_id: u64,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let room_id: RoomId = room_id.into();
+        let init = self.room_init(room_id.id()).await?;
+        let Some(room) = init.data else {
+            return Ok(APIResult {
+                code: init.code,
+                message: init.message,
+                ttl: init.ttl,
+                ts: init.ts,
+                data: None,
+            });
+        };
+
+        let ruid = room.uid.to_string();
+        let room_id = room.room_id.to_string();
+        let lot_id = lot_id.to_string();
+        let param = [
+            ("ruid", ruid.as_str()),
+            ("room_id", room_id.as_str()),
+            ("lot_id", lot_id.as_str()),
+            ("spm_id", "444.8.red_envelope.extract"),
+            ("jump_from", ""),
+            ("session_id", ""),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+            ("visit_id", ""),
+        ];
+        let resp = self
+            .client
+            .post("https://api.live.bilibili.com/xlive/lottery-interface/v1/popularityRedPocket/RedPocketDraw")
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+
+    /// 参与天选时刻，`lot_id` 见 `ANCHOR_LOT_START` 消息
+    ///
+    /// 需要发弹幕的天选会由服务端代发 `LotStart::danmu`；需要送礼的天选不会自动送礼，
+    /// 先用 `send_gift` 送出 `LotStart::gift_id` 再调用
+    pub async fn join_anchor_lot(
+        &self,
+        room_id: impl Into<RoomId>,
+        lot_id: u64,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?.to_string();
+        let lot_id = lot_id.to_string();
+        let param = [
+            ("id", lot_id.as_str()),
+            ("roomid", room_id.as_str()),
+            ("platform", "pc"),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let resp = self
+            .client
+            .post("https://api.live.bilibili.com/xlive/lottery-interface/v1/Anchor/Join")
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+}
@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult, BarrageResult, RoomId, SendBarrageError};
+
+/// 打卡时发送的弹幕，每天在直播间发送的第一条弹幕会增加亲密度
+const SIGNIN_BARRAGE: &str = "打卡";
+
+/// 粉丝勋章，websocket 消息和 http 接口共用
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct Medal {
+    #[serde(default)]
+    pub medal_id: u64,
+    #[serde(default)]
+    #[serde(alias = "roomid")]
+    pub anchor_roomid: u32,
+    #[serde(default)]
+    pub guard_level: u32,
+    #[serde(default)]
+    #[serde(alias = "level")]
+    pub medal_level: u32,
+    #[serde(default)]
+    pub medal_name: String,
+    /// 亲密度
+    #[serde(default)]
+    pub intimacy: u64,
+    #[serde(default)]
+    pub next_intimacy: u64,
+}
+
+/// 自己拥有的粉丝勋章
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct OwnedMedal {
+    /// 主播 uid
+    #[serde(default)]
+    pub target_id: u64,
+    /// 主播名字
+    #[serde(default)]
+    pub target_name: String,
+    /// 主播的直播间，没有直播间时为 0
+    #[serde(default)]
+    pub room_id: u64,
+    #[serde(default)]
+    pub medal_id: u64,
+    #[serde(default)]
+    pub medal_name: String,
+    #[serde(default)]
+    pub level: u32,
+    #[serde(default)]
+    pub intimacy: u64,
+    #[serde(default)]
+    pub next_intimacy: u64,
+    /// 今天获得的亲密度
+    #[serde(default)]
+    pub today_feed: u64,
+    /// 每天能获得的亲密度上限
+    #[serde(default)]
+    pub day_limit: u64,
+}
+
+#[derive(Deserialize, Default)]
+struct MedalPanel {
+    #[serde(default)]
+    list: Vec<MedalPanelItem>,
+    /// 佩戴中和置顶的勋章，只在第一页返回
+    #[serde(default)]
+    special_list: Vec<MedalPanelItem>,
+    #[serde(default)]
+    page_info: MedalPanelPage,
+}
+
+#[derive(Deserialize, Default)]
+struct MedalPanelPage {
+    #[serde(default)]
+    total_page: u32,
+}
+
+#[derive(Deserialize)]
+struct MedalPanelItem {
+    #[serde(default)]
+    medal: OwnedMedal,
+    #[serde(default)]
+    anchor_info: MedalPanelAnchor,
+    #[serde(default)]
+    room_info: MedalPanelRoom,
+}
+
+#[derive(Deserialize, Default)]
+struct MedalPanelAnchor {
+    #[serde(default)]
+    nick_name: String,
+}
+
+#[derive(Deserialize, Default)]
+struct MedalPanelRoom {
+    #[serde(default)]
+    room_id: u64,
+}
+
+impl From<MedalPanelItem> for OwnedMedal {
+    fn from(item: MedalPanelItem) -> Self {
+        OwnedMedal {
+            target_name: item.anchor_info.nick_name,
+            room_id: item.room_info.room_id,
+            ..item.medal
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MedalSignin {
+    pub barrage: APIResult<BarrageResult>,
+    /// 打卡获得的亲密度，只有佩戴的是这个直播间的勋章时才能算出来
+    pub intimacy: Option<u64>,
+}
+
+impl APIClient {
+    /// 佩戴粉丝勋章
+    pub async fn wear_medal(
+        &self,
+        medal_id: u64,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let medal_id = medal_id.to_string();
+        let param = [
+            ("medal_id", medal_id.as_str()),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let resp = self
+            .client
+            .post("https://api.live.bilibili.com/xlive/web-room/v1/fansMedal/wear")
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+
+    /// 取下当前佩戴的粉丝勋章
+    pub async fn take_off_medal(&self) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let param = [
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let resp = self
+            .client
+            .post("https://api.live.bilibili.com/xlive/web-room/v1/fansMedal/take_off")
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+
+    /// 获取当前佩戴的粉丝勋章，没有佩戴时 `data` 为 `None`
+    pub async fn get_worn_medal(&self) -> Result<APIResult<Medal>, reqwest::Error> {
+        let resp = self
+            .client
+            .get("https://api.live.bilibili.com/live_user/v1/UserInfo/get_weared_medal")
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        // 没有佩戴勋章时 data 是一个空数组
+        let APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data,
+        } = resp.json::<APIResult<serde_json::Value>>().await?;
+        Ok(APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data: data
+                .filter(|v| v.is_object())
+                .and_then(|v| serde_json::from_value(v).ok()),
+        })
+    }
+
+    /// 获取自己拥有的全部粉丝勋章，会逐页查询直到最后一页
+    ///
+    /// 遇到错误时返回这一页的结果，`data` 为 `None`
+    pub async fn get_medal_list(&self) -> Result<APIResult<Vec<OwnedMedal>>, reqwest::Error> {
+        let mut medals = vec![];
+        let mut page = 1;
+        loop {
+            let resp = self
+                .client
+                .get(format!(
+                    "https://api.live.bilibili.com/xlive/app-ucenter/v1/fansMedal/panel?page={page}&page_size=50"
+                ))
+                .headers(self.default_headers("https://link.bilibili.com"))
+                .send_retry(self)
+                .await?;
+
+            let APIResult {
+                code,
+                message,
+                ttl,
+                ts,
+                data,
+            } = resp.json::<APIResult<MedalPanel>>().await?;
+            let Some(panel) = data.filter(|_| code == 0) else {
+                return Ok(APIResult {
+                    code,
+                    message,
+                    ttl,
+                    ts,
+                    data: None,
+                });
+            };
+
+            medals.extend(panel.special_list.into_iter().map(OwnedMedal::from));
+            medals.extend(panel.list.into_iter().map(OwnedMedal::from));
+            if page >= panel.page_info.total_page {
+                return Ok(APIResult {
+                    code,
+                    message,
+                    ttl,
+                    ts,
+                    data: Some(medals),
+                });
+            }
+            page += 1;
+        }
+    }
+
+    /// 粉丝勋章打卡，在直播间发送一条打卡弹幕
+    ///
+    /// 如果当前佩戴的是这个直播间的勋章，会比较发送前后的亲密度得到这次获得的亲密度，
+    /// 亲密度已经满了或者今天已经打过卡时为 `Some(0)`
+    pub async fn medal_signin(
+        &self,
+        room_id: impl Into<RoomId>,
+    ) -> Result<MedalSignin, SendBarrageError> {
+        let room_id = self.resolve_room_id(room_id).await?;
+        let room_medal = |r: APIResult<Medal>| {
+            r.data
+                .filter(|m| m.anchor_roomid as u64 == room_id)
+                .map(|m| m.intimacy)
+        };
+        let before = room_medal(self.get_worn_medal().await?);
+
+        let barrage = self
+            .send_barrage(RoomId::Real(room_id), SIGNIN_BARRAGE)
+            .await?;
+
+        let intimacy = match before {
+            Some(before) if barrage.code == 0 => {
+                // 亲密度不是实时更新的
+                tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+                room_medal(self.get_worn_medal().await?).map(|after| after.saturating_sub(before))
+            }
+            _ => None,
+        };
+
+        Ok(MedalSignin { barrage, intimacy })
+    }
+}
@@ -1,19 +1,74 @@
-use std::{sync::Arc, time::Duration};
+pub mod admin;
+pub mod anchor;
+pub mod area;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod buvid;
+pub mod daily;
+pub mod error;
+pub mod gift;
+pub mod like;
+pub mod login;
+pub mod lottery;
+pub mod medal;
+#[cfg(feature = "login_qr")]
+pub mod qr;
+pub mod retry;
+pub mod room;
+pub mod search;
+pub mod session;
+pub mod share;
+pub mod ticket;
+pub mod tv_login;
+pub mod user;
+pub mod watch;
+
+pub use anchor::{RtmpInfo, StartLiveInfo};
+pub use area::{AreaRoom, AreaRoomList, ParentArea, SubArea};
+pub use daily::{run_daily_tasks, DailyTaskConfig, DailyTaskError, DailyTaskReport};
+pub use error::ApiError;
+pub use gift::{
+    BlindGift, CoinType, GiftConfig, GiftInfo, GiftSendResult, SendGift, SendGiftError, Wallet,
+};
+pub use like::spawn_like_loop;
+pub use login::{CaptchaResult, PasswordLogin, PasswordLoginError, SmsLogin, SmsLoginError};
+pub use medal::{Medal, MedalSignin, OwnedMedal};
+#[cfg(feature = "login_qr")]
+pub use qr::QrError;
+pub use retry::RetryPolicy;
+use retry::SendRetry;
+pub use room::{
+    GoldRank, GuardList, GuardUser, HistoryDanmu, OnlineUser, RoomId, RoomInit, RoomStatus,
+};
+pub use search::{LiveRoomItem, LiveSearchResult, SearchResult, UserItem, UserSearchResult};
+pub use session::{parse_netscape_cookies, LoginSession, Profiles, Session, SessionError};
+pub use share::ShareResult;
+pub use ticket::BiliTicket;
+pub use tv_login::{AppToken, TvLogin};
+pub use user::{NavInfo, RelationAction, UserInfo, WbiImg};
+pub use watch::{spawn_watch_heartbeat, WebHeartBeat};
+
+use std::{
+    collections::HashMap,
+    sync::{atomic::AtomicU64, Arc},
+    time::Duration,
+};
 
 use reqwest::{
     cookie::{CookieStore, Jar},
-    header::{ACCEPT, ORIGIN, REFERER, USER_AGENT},
+    header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, ORIGIN, REFERER, USER_AGENT},
     Client,
 };
 use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 
-const BILI_URL: &'static str = "https://bilibili.com";
+const BILI_URL: &str = "https://bilibili.com";
 
-const COOKIE_USER_ID: &'static str = "DedeUserID=";
-const COOKIE_SESSDATA: &'static str = "SESSDATA=";
-const COOKIE_BILI_JCT: &'static str = "bili_jct=";
+const COOKIE_USER_ID: &str = "DedeUserID=";
+const COOKIE_SESSDATA: &str = "SESSDATA=";
+const COOKIE_BILI_JCT: &str = "bili_jct=";
 
-const UA: &'static str =
+const UA: &str =
     "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/81.0.4044.138 Safari/537.36";
 
 #[derive(Debug, Clone, Default)]
@@ -29,18 +84,22 @@ pub struct UserToken {
 /// * 在保存了 tokens 的情况下新建一个 `APIClient` 实例
 ///
 /// ```no_run
-/// let tokens:Vec<String> = /* 从你保存的地方读回来 */
+/// # use bilili_rs::api::{APIClient, UserToken};
+/// let tokens: Vec<String> = vec![/* 从你保存的地方读回来 */];
 /// let (token, jar) = UserToken::create_from_tokens(&tokens).unwrap();
-/// let client = APIClient::new(token, jar).unwrap();
+/// let client = APIClient::new(token, jar, tokens).unwrap();
 /// ```
 ///
 /// * 在没有保存 tokens 的情况下，可以通过扫码登录获取 `APIClient`
 ///
 /// ```no_run
-/// let login_url = LoginUrl::get_login_url().await.unwrap();
-/// let url = login_url.url;
+/// # use bilili_rs::api::LoginUrl;
+/// # async fn run() {
+/// let login_url = LoginUrl::get_login_url().await.unwrap().data.unwrap();
+/// let url = &login_url.url;
 /// /* 把 url 生成一个 qrcode 让用户去扫码确认登录 */
 /// let client = login_url.poll_tokens().await.unwrap().data.unwrap();
+/// # }
 /// ```
 ///
 #[derive(Debug, Clone)]
@@ -49,6 +108,22 @@ pub struct APIClient {
     pub token: UserToken,
     pub jar: Arc<Jar>,
     pub cookies: Vec<String>,
+    pub user_agent: String,
+    /// 扫码登录时返回，用于刷新 cookie
+    pub refresh_token: String,
+    /// TV 端扫码登录时返回，见 `TvLogin`
+    pub app_token: Option<AppToken>,
+    /// 当前 `bili_ticket` 的过期时间（unix 秒），clone 出来的 client 共享同一个
+    ticket_expires_at: Arc<AtomicU64>,
+    /// 两条弹幕之间的最小间隔
+    barrage_interval: Duration,
+    retry: RetryPolicy,
+    /// 下一条弹幕最早可以发送的时间，clone 出来的 client 共享同一个
+    barrage_next: Arc<tokio::sync::Mutex<tokio::time::Instant>>,
+    /// 短号到真实房间号的缓存，见 `resolve_room_id`
+    room_ids: Arc<std::sync::Mutex<HashMap<u64, u64>>>,
+    /// `ensure_buvid` 获取到的 cookie，clone 出来的 client 共享同一个
+    buvid: Arc<std::sync::Mutex<buvid::BuvidState>>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -57,6 +132,9 @@ pub enum CheckCookieError {
     EmptyCookie,
     #[error("Illegal cookie")]
     IllegalCookie,
+    /// cookie 格式正确但已经在服务端失效，需要重新登录
+    #[error("token expired, please re-login")]
+    Expired,
     #[error("cookie error {0}")]
     CookieToStrError(#[from] reqwest::header::ToStrError),
 }
@@ -106,26 +184,157 @@ impl UserToken {
     }
 }
 
-impl APIClient {
-    pub fn new(
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use bilili_rs::api::{APIClient, UserToken};
+/// # let tokens: Vec<String> = vec![];
+/// let (token, jar) = UserToken::create_from_tokens(&tokens).unwrap();
+/// let client = APIClient::builder()
+///     .timeout(Duration::from_secs(10))
+///     .proxy(reqwest::Proxy::all("http://127.0.0.1:8080").unwrap())
+///     .build(token, jar, tokens)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct APIClientBuilder {
+    connect_timeout: Duration,
+    timeout: Duration,
+    user_agent: String,
+    proxy: Option<reqwest::Proxy>,
+    barrage_interval: Duration,
+    retry: RetryPolicy,
+    headers: HeaderMap,
+}
+
+impl Default for APIClientBuilder {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(3),
+            timeout: Duration::from_secs(5),
+            user_agent: UA.to_string(),
+            proxy: None,
+            barrage_interval: Duration::from_secs(1),
+            retry: RetryPolicy::default(),
+            headers: HeaderMap::new(),
+        }
+    }
+}
+
+impl APIClientBuilder {
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn user_agent<S: Into<String>>(mut self, user_agent: S) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// 所有请求都走这个代理，包括扫码登录
+    ///
+    /// 没有设置时会读取 `HTTP_PROXY` / `HTTPS_PROXY` / `ALL_PROXY` 环境变量
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// 两条弹幕之间的最小间隔，默认 1 秒，发得太快容易被风控或者被吞掉
+    pub fn barrage_interval(mut self, barrage_interval: Duration) -> Self {
+        self.barrage_interval = barrage_interval;
+        self
+    }
+
+    /// 请求超时或连接失败后最多重试 `retries` 次，默认 2 次，只重试 GET 请求
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retry.retries = retries;
+        self
+    }
+
+    /// 完整的重试策略，可以打开 POST 请求的重试
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 所有请求都带上这个请求头，包括扫码登录
+    ///
+    /// 每个接口自己设置的 `User-Agent`、`Referer` 和 `Origin` 优先，修改 UA 请用 `user_agent`
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// 登录还没有 `APIClient` 时使用，同 `APIClient::default_headers`
+    pub fn default_headers(&self, referer: &str) -> HeaderMap {
+        endpoint_headers(&self.user_agent, referer)
+    }
+
+    fn build_http_client(&self, jar: Arc<Jar>) -> Result<Client, reqwest::Error> {
+        let mut builder = Client::builder()
+            .default_headers(self.headers.clone())
+            .cookie_provider(jar)
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout);
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        builder.build()
+    }
+
+    pub fn build(
+        self,
         token: UserToken,
         jar: Arc<Jar>,
         cookies: Vec<String>,
-    ) -> Result<Self, reqwest::Error> {
-        let client = Client::builder()
-            .cookie_provider(jar.clone())
-            .connect_timeout(Duration::from_secs(3))
-            .timeout(Duration::from_secs(5))
-            .build()?;
-        Ok(Self {
-            client,
+    ) -> Result<APIClient, reqwest::Error> {
+        Ok(APIClient {
+            client: self.build_http_client(jar.clone())?,
             token,
             jar,
             cookies,
+            user_agent: self.user_agent,
+            refresh_token: String::new(),
+            app_token: None,
+            ticket_expires_at: Arc::new(AtomicU64::new(0)),
+            barrage_interval: self.barrage_interval,
+            retry: self.retry,
+            barrage_next: Arc::new(tokio::sync::Mutex::new(tokio::time::Instant::now())),
+            room_ids: Default::default(),
+            buvid: Default::default(),
         })
     }
 }
 
+impl APIClient {
+    /// 每个请求都要带的 `User-Agent`、`Referer` 和由 `referer` 得到的 `Origin`
+    ///
+    /// 直播相关的接口用 `https://live.bilibili.com`，主站的接口用 `https://www.bilibili.com`，
+    /// 登录相关的接口用 `https://passport.bilibili.com`，不对时容易被拦截（`code: -412`）
+    pub fn default_headers(&self, referer: &str) -> HeaderMap {
+        endpoint_headers(&self.user_agent, referer)
+    }
+
+    pub fn builder() -> APIClientBuilder {
+        APIClientBuilder::default()
+    }
+
+    pub fn new(
+        token: UserToken,
+        jar: Arc<Jar>,
+        cookies: Vec<String>,
+    ) -> Result<Self, reqwest::Error> {
+        Self::builder().build(token, jar, cookies)
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct QrResult {
     url: String,
@@ -154,122 +363,143 @@ pub enum QrResultError {
     UnknownError { code: i32, message: String },
     #[error("HttpError {0}")]
     HttpError(#[from] reqwest::Error),
+    #[error("CookieError {0}")]
+    CookieError(#[from] CheckCookieError),
 }
 
-impl Into<Result<QrResult, QrResultError>> for QrResult {
-    fn into(self) -> Result<QrResult, QrResultError> {
-        match self.code {
+impl From<QrResult> for Result<QrResult, QrResultError> {
+    fn from(r: QrResult) -> Self {
+        match r.code {
             86101 => Err(QrResultError::NotScaned),
             86038 => Err(QrResultError::QrExpired),
             86090 => Err(QrResultError::ScanedNotConfirm),
-            0 => Ok(self),
+            0 => Ok(r),
             _ => Err(QrResultError::UnknownError {
-                code: self.code,
-                message: self.message,
+                code: r.code,
+                message: r.message,
             }),
         }
     }
 }
 
+fn endpoint_headers(user_agent: &str, referer: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(ua) = HeaderValue::from_str(user_agent) {
+        headers.insert(USER_AGENT, ua);
+    }
+    if let Ok(url) = url::Url::parse(referer) {
+        if let Ok(origin) = HeaderValue::from_str(&url.origin().ascii_serialization()) {
+            headers.insert(ORIGIN, origin);
+        }
+    }
+    if let Ok(referer) = HeaderValue::from_str(referer) {
+        headers.insert(REFERER, referer);
+    }
+    headers
+}
+
 async fn check_qrcode(
     client: &Client,
     qrcode_key: &str,
+    builder: &APIClientBuilder,
 ) -> Result<(APIResult<QrResult>, Vec<String>), reqwest::Error> {
     log::info!("get_bili_client by {}", qrcode_key);
     let form_param = [("qrcode_key", qrcode_key), ("source", "main-fe-header")];
     let resp = client
         .get(format!("https://passport.bilibili.com/x/passport-login/web/qrcode/poll?qrcode_key={}&source=main-fe-header", qrcode_key))
-        .header(USER_AGENT, UA)
+        .headers(builder.default_headers("https://www.bilibili.com"))
         .header(ACCEPT, "application/json, text/plain, */*")
-        .header(REFERER, "https://www.bilibili.com")
-        .header(ORIGIN, "https://www.bilibili.com")
         .form(&form_param)
         .send()
         .await?;
 
+    let cookies = collect_set_cookies(&resp);
+
+    Ok((resp.json::<APIResult<QrResult>>().await?, cookies))
+}
+
+fn collect_set_cookies(resp: &reqwest::Response) -> Vec<String> {
     let header_cookies = resp.headers().get_all("set-cookie");
     let mut cookies = Vec::new();
 
     for cookie_value in header_cookies {
         match cookie_value.to_str() {
             Ok(cookie) => {
-                cookies.push(cookie.to_string());
+                merge_cookie_list(&mut cookies, &[cookie.to_string()]);
             }
             Err(e) => {
                 log::warn!("login cookie to str error : {:?}", e)
             }
         }
     }
+    cookies
+}
 
-    Ok((resp.json::<APIResult<QrResult>>().await?, cookies))
+/// cookie 的名字，`SESSDATA=xxx; Path=/` 返回 `SESSDATA`
+fn cookie_name(cookie: &str) -> &str {
+    let pair = cookie.split(';').next().unwrap_or_default();
+    pair.split('=').next().unwrap_or_default().trim()
 }
 
-async fn poll_tokens_from_bili(
-    client: &Client,
-    login_url: &LoginUrl,
-) -> Result<(APIResult<QrResult>, Vec<String>), QrResultError> {
-    'check: loop {
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+/// 把 `new` 合并进 `cookies`，同名的 cookie 会被替换而不是追加
+fn merge_cookie_list(cookies: &mut Vec<String>, new: &[String]) {
+    for cookie in new {
+        let name = cookie_name(cookie);
+        cookies.retain(|c| cookie_name(c) != name);
+        cookies.push(cookie.clone());
+    }
+}
 
-        let (
-            APIResult {
-                code,
-                message,
-                ttl,
-                ts,
-                data,
-            },
-            cookies,
-        ) = check_qrcode(client, &login_url.qrcode_key).await?;
+impl APIClient {
+    /// 把新的 cookie 写入 `jar`，同时替换 `cookies` 里同名的 cookie，
+    /// 避免刷新 cookie 之后保存的 session 里出现两个 `SESSDATA`
+    ///
+    /// 新的 cookie 里有登录信息时会更新 `token`
+    pub fn merge_cookies(&mut self, new: &[String]) {
+        let domain_url = BILI_URL.parse().unwrap();
+        for cookie in new {
+            self.jar.add_cookie_str(cookie, &domain_url);
+        }
+        merge_cookie_list(&mut self.cookies, new);
+        match UserToken::create_from_jar(self.jar.clone()) {
+            Ok(token) => self.token = token,
+            Err(e) => log::debug!("merge_cookies keep old token: {}", e),
+        }
+    }
+}
 
-        if code == 0 {
-            if let Some(r) = data {
-                let r: Result<QrResult, QrResultError> = r.into();
-                match r {
-                    Ok(r) => {
-                        log::info!("get_bili_client success");
-                        return Ok((
-                            APIResult {
-                                code,
-                                message,
-                                ttl,
-                                ts,
-                                data: Some(r),
-                            },
-                            cookies,
-                        ));
-                    }
-                    Err(e) => {
-                        log::info!("get_bili_client error: {}", e);
-                        match e {
-                            QrResultError::NotScaned => {
-                                continue 'check;
-                            }
-                            QrResultError::ScanedNotConfirm => {
-                                continue 'check;
-                            }
-                            e => {
-                                return Err(e);
-                            }
-                        }
-                    }
-                }
-            }
-        } else {
-            return Ok((
-                APIResult {
-                    code,
-                    message,
-                    ttl,
-                    ts,
-                    data: None,
-                },
-                cookies,
-            ));
+/// 扫码登录的轮询参数
+#[derive(Debug, Clone, Copy)]
+pub struct PollOptions {
+    /// 两次查询扫码结果的间隔
+    pub interval: Duration,
+    /// 超过这个时间还没有确认登录就返回 `QrResultError::QrExpired`
+    pub timeout: Duration,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            // 二维码有效期为 180 秒
+            timeout: Duration::from_secs(180),
         }
     }
 }
 
+/// 单次查询扫码结果
+#[derive(Debug, Clone)]
+pub enum LoginStep {
+    /// 未扫码
+    NotScanned,
+    /// 已扫码未确认
+    Scanned,
+    /// 已确认登录
+    Confirmed(Box<APIClient>),
+    /// 二维码已失效
+    Expired,
+}
+
 // api
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -297,27 +527,131 @@ pub enum LoginError {
     QrResultError(#[from] QrResultError),
     #[error("HttpError {0}")]
     HttpError(#[from] reqwest::Error),
+    #[error("Cancelled")]
+    Cancelled,
 }
 
 impl LoginUrl {
     pub async fn get_login_url() -> Result<APIResult<Self>, reqwest::Error> {
-        // https://passport.bilibili.com/x/passport-login/web/qrcode/generate?source=main-fe-header
-        let resp = reqwest::get(
-            "https://passport.bilibili.com/x/passport-login/web/qrcode/generate?source=main-fe-header",
-        )
-        .await?;
+        Self::get_login_url_with(&APIClient::builder()).await
+    }
+
+    /// 使用 `builder` 中的代理、超时和 UA 获取登录二维码
+    pub async fn get_login_url_with(
+        builder: &APIClientBuilder,
+    ) -> Result<APIResult<Self>, reqwest::Error> {
+        let client = builder.build_http_client(Arc::new(Jar::default()))?;
+        let resp = client
+            .get("https://passport.bilibili.com/x/passport-login/web/qrcode/generate?source=main-fe-header")
+            .headers(builder.default_headers("https://www.bilibili.com"))
+            .send()
+            .await?;
         resp.json::<APIResult<LoginUrl>>().await
     }
 
     pub async fn poll_tokens(&self) -> Result<APIResult<APIClient>, LoginError> {
-        let jar = Arc::new(Jar::default());
+        self.poll_tokens_with(APIClient::builder(), PollOptions::default())
+            .await
+    }
+
+    /// 使用 `builder` 中的代理、超时和 UA 轮询扫码结果，登录成功后也用它构建 `APIClient`
+    pub async fn poll_tokens_with(
+        &self,
+        builder: APIClientBuilder,
+        poll: PollOptions,
+    ) -> Result<APIResult<APIClient>, LoginError> {
+        self.poll_tokens_until(builder, poll, &CancellationToken::new())
+            .await
+    }
+
+    /// 同 `poll_tokens`，`cancel` 被取消后在下一次查询之前返回 `LoginError::Cancelled`
+    pub async fn poll_tokens_with_cancel(
+        &self,
+        cancel: CancellationToken,
+    ) -> Result<APIResult<APIClient>, LoginError> {
+        self.poll_tokens_until(APIClient::builder(), PollOptions::default(), &cancel)
+            .await
+    }
+
+    async fn poll_tokens_until(
+        &self,
+        builder: APIClientBuilder,
+        poll: PollOptions,
+        cancel: &CancellationToken,
+    ) -> Result<APIResult<APIClient>, LoginError> {
+        let client = builder.build_http_client(Arc::new(Jar::default()))?;
+
+        let r = tokio::time::timeout(poll.timeout, async {
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(poll.interval) => {}
+                    _ = cancel.cancelled() => return Err(LoginError::Cancelled),
+                }
+                let r = self.poll_step(&client, &builder).await?;
+                match r.data {
+                    Some(LoginStep::NotScanned) | Some(LoginStep::Scanned) => continue,
+                    Some(LoginStep::Expired) => return Err(QrResultError::QrExpired.into()),
+                    Some(LoginStep::Confirmed(api_client)) => {
+                        log::info!("get_bili_client success");
+                        return Ok(APIResult {
+                            code: r.code,
+                            message: r.message,
+                            ttl: r.ttl,
+                            ts: r.ts,
+                            data: Some(*api_client),
+                        });
+                    }
+                    None => {
+                        return Ok(APIResult {
+                            code: r.code,
+                            message: r.message,
+                            ttl: r.ttl,
+                            ts: r.ts,
+                            data: None,
+                        })
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap_or(Err(QrResultError::QrExpired.into()))?;
 
-        let client = Client::builder()
-            .cookie_provider(jar.clone())
-            .connect_timeout(Duration::from_secs(3))
-            .timeout(Duration::from_secs(5))
-            .build()?;
+        Ok(r)
+    }
 
+    /// 查询一次扫码结果，不会等待，由调用方决定轮询的频率
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use bilili_rs::api::{LoginStep, LoginUrl};
+    /// # async fn run(login_url: LoginUrl) {
+    /// let client = reqwest::Client::new();
+    /// loop {
+    ///     match login_url.poll_once(&client).await.unwrap() {
+    ///         LoginStep::NotScanned => {}
+    ///         LoginStep::Scanned => { /* 提示用户在手机上确认 */ }
+    ///         LoginStep::Confirmed(api_client) => break,
+    ///         LoginStep::Expired => { /* 重新获取二维码 */ }
+    ///     }
+    ///     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    /// }
+    /// # }
+    /// ```
+    pub async fn poll_once(&self, client: &Client) -> Result<LoginStep, QrResultError> {
+        let r = self.poll_step(client, &APIClient::builder()).await?;
+        r.data.ok_or(QrResultError::UnknownError {
+            code: r.code,
+            message: r.message.unwrap_or_default(),
+        })
+    }
+
+    /// 接口本身返回错误时 `data` 为 `None`
+    async fn poll_step(
+        &self,
+        client: &Client,
+        builder: &APIClientBuilder,
+    ) -> Result<APIResult<LoginStep>, QrResultError> {
         let (
             APIResult {
                 code,
@@ -327,65 +661,246 @@ impl LoginUrl {
                 data,
             },
             cookies,
-        ) = poll_tokens_from_bili(&client, self).await?;
+        ) = check_qrcode(client, &self.qrcode_key, builder).await?;
 
-        if code != 0 || data.is_none() {
-            Ok(APIResult {
-                code,
-                message,
-                ttl,
-                ts,
-                data: None,
-            })
-        } else {
-            let token = UserToken::create_from_jar(jar.clone()).unwrap();
-            let client = APIClient::new(token, jar, cookies)?;
-            Ok(APIResult {
-                code,
-                message,
-                ttl,
-                ts,
-                data: Some(client),
-            })
+        let step = match data {
+            Some(r) if code == 0 => {
+                let r: Result<QrResult, QrResultError> = r.into();
+                match r {
+                    Ok(r) => {
+                        let (token, jar) = UserToken::create_from_tokens(&cookies)?;
+                        let mut api_client = builder.clone().build(token, jar, cookies)?;
+                        api_client.refresh_token = r.refresh_token;
+                        if let Err(e) = api_client.ensure_buvid().await {
+                            log::warn!("ensure_buvid error: {}", e);
+                        }
+                        Some(LoginStep::Confirmed(Box::new(api_client)))
+                    }
+                    Err(QrResultError::NotScaned) => Some(LoginStep::NotScanned),
+                    Err(QrResultError::ScanedNotConfirm) => Some(LoginStep::Scanned),
+                    Err(QrResultError::QrExpired) => Some(LoginStep::Expired),
+                    Err(e) => {
+                        log::info!("get_bili_client error: {}", e);
+                        return Err(e);
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        Ok(APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data: step,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BarrageOptions {
+    /// 十进制 RGB，默认白色
+    pub color: u32,
+    pub fontsize: u32,
+    /// 1 是滚动弹幕 4 是底部弹幕 5 是顶部弹幕
+    pub mode: u8,
+    pub bubble: u32,
+    /// 距离上一条弹幕不足 `APIClientBuilder::barrage_interval` 时等待，
+    /// 为 `false` 时直接返回 `SendBarrageError::TooFrequent`
+    pub wait: bool,
+    /// `send_long_barrage` 每条弹幕的最大字数
+    pub max_len: usize,
+}
+
+impl Default for BarrageOptions {
+    fn default() -> Self {
+        Self {
+            color: 0xffffff,
+            fontsize: 25,
+            mode: 1,
+            bubble: 0,
+            wait: true,
+            max_len: 20,
         }
     }
 }
 
+fn check_barrage(barrage: &str, opts: &BarrageOptions) -> Result<(), SendBarrageError> {
+    if !matches!(opts.mode, 1 | 4 | 5) {
+        return Err(SendBarrageError::InvalidMode(opts.mode));
+    }
+    if barrage.trim().is_empty() {
+        return Err(SendBarrageError::Empty);
+    }
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SendBarrageError {
+    #[error("invalid barrage mode {0}")]
+    InvalidMode(u8),
+    #[error("empty barrage")]
+    Empty,
+    /// 还需要等待的时间
+    #[error("send barrage too frequent, retry after {0:?}")]
+    TooFrequent(Duration),
+    #[error("HttpError {0}")]
+    HttpError(#[from] reqwest::Error),
+}
+
+/// 发送弹幕的返回结果
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct BarrageResult {
+    #[serde(default)]
+    pub mode_info: BarrageModeInfo,
+    /// 弹幕被云屏蔽，只有自己能看到，接口返回的 `message` 为 `f` 或 `k`
+    #[serde(default)]
+    pub shielded: bool,
+    /// 服务端保存的内容和发送的不一致，一般是超过长度被截断
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct BarrageModeInfo {
+    #[serde(default)]
+    pub mode: u32,
+    #[serde(default)]
+    pub show_player_type: u32,
+    /// json 字符串，包含服务端实际保存的弹幕内容
+    #[serde(default)]
+    pub extra: String,
+}
+
+impl BarrageResult {
+    /// 服务端实际保存的弹幕内容
+    pub fn content(&self) -> Option<String> {
+        let extra: serde_json::Value = serde_json::from_str(&self.mode_info.extra).ok()?;
+        extra["content"].as_str().map(|s| s.to_string())
+    }
+}
+
 impl APIClient {
     pub async fn send_barrage(
         &self,
-        room_id: &str,
+        room_id: impl Into<RoomId>,
         barrage: &str,
-    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+    ) -> Result<APIResult<BarrageResult>, SendBarrageError> {
+        self.send_barrage_with(room_id, barrage, BarrageOptions::default())
+            .await
+    }
+
+    pub async fn send_barrage_with(
+        &self,
+        room_id: impl Into<RoomId>,
+        barrage: &str,
+        opts: BarrageOptions,
+    ) -> Result<APIResult<BarrageResult>, SendBarrageError> {
+        check_barrage(barrage, &opts)?;
+        let room_id = self.resolve_room_id(room_id).await?;
+        self.ensure_risk_cookies().await;
+
+        // 先占好发送的时间点再释放锁，其它发送不用等这条请求返回
+        let slot = {
+            let mut next = self.barrage_next.lock().await;
+            let now = tokio::time::Instant::now();
+            if *next > now && !opts.wait {
+                return Err(SendBarrageError::TooFrequent(*next - now));
+            }
+            let slot = (*next).max(now);
+            *next = slot + self.barrage_interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .expect("Time went backwards");
         let now = format!("{}", now.as_secs());
+        let color = opts.color.to_string();
+        let fontsize = opts.fontsize.to_string();
+        let mode = opts.mode.to_string();
+        let bubble = opts.bubble.to_string();
+        let room_id = room_id.to_string();
         let param = [
-            ("color", "16777215"), // 默认白色
-            ("fontsize", "25"),
-            ("mode", "1"), // 1 是滚动弹幕 4 是底部弹幕
+            ("color", color.as_str()),
+            ("fontsize", fontsize.as_str()),
+            ("mode", mode.as_str()),
             ("msg", barrage),
             ("rnd", now.as_str()),
-            ("roomid", room_id),
-            ("bubble", "0"),
+            ("roomid", room_id.as_str()),
+            ("bubble", bubble.as_str()),
             ("csrf_token", self.token.csrf.as_str()),
             ("csrf", self.token.csrf.as_str()),
         ];
         let resp = self
             .client
             .post("https://api.live.bilibili.com/msg/send")
-            .header(USER_AGENT, UA)
-            .header(reqwest::header::REFERER, "https://live.bilibili.com")
+            .headers(self.default_headers("https://live.bilibili.com"))
             .form(&param)
-            .send()
+            .send_retry(self)
             .await?;
 
-        resp.json::<APIResult<serde_json::Value>>().await
+        let APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data,
+        } = resp.json::<APIResult<serde_json::Value>>().await?;
+        // 发得太快被风控时推迟下一条弹幕
+        if let Some(retry_after) = ApiError::new(code, String::new()).retry_after() {
+            let mut next = self.barrage_next.lock().await;
+            let retry_at = tokio::time::Instant::now() + retry_after.max(self.barrage_interval);
+            *next = (*next).max(retry_at);
+        }
+        // 失败时 data 可能是 `[]`
+        let data = data
+            .and_then(|v| serde_json::from_value::<BarrageResult>(v).ok())
+            .map(|mut r| {
+                r.shielded = matches!(message.as_deref(), Some("f") | Some("k"));
+                r.truncated = r.content().is_some_and(|c| c != barrage);
+                r
+            });
+
+        Ok(APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data,
+        })
+    }
+
+    /// 把超过 `opts.max_len` 个字的弹幕拆成多条依次发送，遵守弹幕的发送间隔
+    ///
+    /// 某一条发送失败（包括接口返回非 0 的 `code`）时停止发送后面的部分
+    pub async fn send_long_barrage(
+        &self,
+        room_id: impl Into<RoomId>,
+        barrage: &str,
+        opts: BarrageOptions,
+    ) -> Result<Vec<APIResult<BarrageResult>>, SendBarrageError> {
+        check_barrage(barrage, &opts)?;
+        let room_id = RoomId::Real(self.resolve_room_id(room_id).await?);
+        let opts = BarrageOptions { wait: true, ..opts };
+        let chars: Vec<char> = barrage.chars().collect();
+        let mut results = Vec::new();
+        for part in chars.chunks(opts.max_len.max(1)) {
+            let part: String = part.iter().collect();
+            let r = self.send_barrage_with(room_id, &part, opts).await?;
+            let code = r.code;
+            results.push(r);
+            if code != 0 {
+                break;
+            }
+        }
+        Ok(results)
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct DanmuInfoResult {
     #[serde(default)]
     pub business_id: u32,
@@ -401,7 +916,7 @@ pub struct DanmuInfoResult {
     pub token: String,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct LiveHost {
     #[serde(default)]
     pub host: String,
@@ -413,6 +928,26 @@ pub struct LiveHost {
     pub wss_port: u32,
 }
 
+impl LiveHost {
+    /// 不加密的 websocket 地址，`ws_port` 为 0 时使用默认的 80 端口
+    pub fn ws_url(&self) -> String {
+        if self.ws_port == 0 {
+            format!("ws://{}/sub", self.host)
+        } else {
+            format!("ws://{}:{}/sub", self.host, self.ws_port)
+        }
+    }
+
+    /// websocket 地址，`wss_port` 为 0 时使用默认的 443 端口
+    pub fn wss_url(&self) -> String {
+        if self.wss_port == 0 {
+            format!("wss://{}/sub", self.host)
+        } else {
+            format!("wss://{}:{}/sub", self.host, self.wss_port)
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct RoomPlayInfo {
     #[serde(default)]
@@ -426,22 +961,183 @@ pub struct RoomPlayInfo {
     pub is_hidden: bool,
     #[serde(default)]
     pub is_locked: bool,
+    /// 下面这几个字段 `getRoomPlayInfo` 不一定返回，缺少时为空
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub cover: String,
+    #[serde(default)]
+    pub area_name: String,
+    #[serde(default)]
+    pub parent_area_name: String,
+    /// 开播时间，unix 秒，未开播时为 `None` 或 0
+    #[serde(default)]
+    #[serde(alias = "live_time")]
+    pub live_start_time: Option<u64>,
+}
+
+impl RoomPlayInfo {
+    /// 已经直播了多久，未开播时为 `None`
+    pub fn live_duration(&self) -> Option<Duration> {
+        let start = self
+            .live_start_time
+            .filter(|t| *t > 0 && self.live_status == 1)?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs();
+        Some(Duration::from_secs(now.saturating_sub(start)))
+    }
+}
+
+/// 直播流地址，完整的地址为 `host + base_url + query`
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct StreamUrl {
+    /// http_stream / http_hls
+    pub protocol: String,
+    /// flv / ts / fmp4
+    pub format: String,
+    /// avc / hevc
+    pub codec: String,
+    pub qn: u32,
+    pub host: String,
+    pub base_url: String,
+    pub query: String,
+}
+
+impl StreamUrl {
+    pub fn url(&self) -> String {
+        format!("{}{}{}", self.host, self.base_url, self.query)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(from = "RawPlayUrlResult")]
+pub struct PlayUrlInfo {
+    pub room_id: u64,
+    /// 0 关播, 1 直播, 2 轮播
+    pub live_status: u32,
+    /// 可选的画质
+    pub accept_qn: Vec<u32>,
+    pub streams: Vec<StreamUrl>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawPlayUrlResult {
+    #[serde(default)]
+    room_id: u64,
+    #[serde(default)]
+    live_status: u32,
+    #[serde(default)]
+    playurl_info: Option<RawPlayUrlInfo>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawPlayUrlInfo {
+    #[serde(default)]
+    playurl: Option<RawPlayUrl>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawPlayUrl {
+    #[serde(default)]
+    stream: Vec<RawStream>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawStream {
+    #[serde(default)]
+    protocol_name: String,
+    #[serde(default)]
+    format: Vec<RawStreamFormat>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawStreamFormat {
+    #[serde(default)]
+    format_name: String,
+    #[serde(default)]
+    codec: Vec<RawStreamCodec>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawStreamCodec {
+    #[serde(default)]
+    codec_name: String,
+    #[serde(default)]
+    current_qn: u32,
+    #[serde(default)]
+    accept_qn: Vec<u32>,
+    #[serde(default)]
+    base_url: String,
+    #[serde(default)]
+    url_info: Vec<RawUrlInfo>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawUrlInfo {
+    #[serde(default)]
+    host: String,
+    #[serde(default)]
+    extra: String,
+}
+
+impl From<RawPlayUrlResult> for PlayUrlInfo {
+    fn from(raw: RawPlayUrlResult) -> Self {
+        let mut info = PlayUrlInfo {
+            room_id: raw.room_id,
+            live_status: raw.live_status,
+            ..Default::default()
+        };
+
+        let streams = raw
+            .playurl_info
+            .and_then(|i| i.playurl)
+            .map(|p| p.stream)
+            .unwrap_or_default();
+
+        for stream in streams {
+            for format in stream.format {
+                for codec in format.codec {
+                    for qn in &codec.accept_qn {
+                        if !info.accept_qn.contains(qn) {
+                            info.accept_qn.push(*qn);
+                        }
+                    }
+                    for url_info in codec.url_info {
+                        info.streams.push(StreamUrl {
+                            protocol: stream.protocol_name.clone(),
+                            format: format.format_name.clone(),
+                            codec: codec.codec_name.clone(),
+                            qn: codec.current_qn,
+                            host: url_info.host,
+                            base_url: codec.base_url.clone(),
+                            query: url_info.extra,
+                        });
+                    }
+                }
+            }
+        }
+
+        info
+    }
 }
 
 impl APIClient {
     /// 获取弹幕服务器信息
     pub async fn get_danmu_info(
         &self,
-        room_id: u64,
+        room_id: impl Into<RoomId>,
     ) -> Result<APIResult<DanmuInfoResult>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?;
         let resp = self
             .client
             .get(format!(
                 "https://api.live.bilibili.com/xlive/web-room/v1/index/getDanmuInfo?id={}&type=0",
                 room_id
             ))
-            .header(USER_AGENT, UA)
-            .send()
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
             .await?;
 
         resp.json::<APIResult<DanmuInfoResult>>().await
@@ -450,30 +1146,53 @@ impl APIClient {
     /// 获取直播间信息
     pub async fn get_room_play_info(
         &self,
-        room_id: u64,
+        room_id: impl Into<RoomId>,
     ) -> Result<APIResult<RoomPlayInfo>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?;
         let resp = self
             .client
             .get(format!(
                 "https://api.live.bilibili.com/xlive/web-room/v2/index/getRoomPlayInfo?room_id={room_id}&protocol=0,1&format=0,1,2&codec=0,1,2&qn=0&platform=web&ptype=8&dolby=5&panorama=1"
             ))
-            .header(USER_AGENT, UA)
-            .send()
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
             .await
             ?;
 
         resp.json::<APIResult<RoomPlayInfo>>().await
     }
+
+    /// 获取直播流地址，`qn` 为期望的画质，实际可选画质见返回的 `accept_qn`
+    pub async fn get_live_stream_url(
+        &self,
+        room_id: impl Into<RoomId>,
+        qn: u32,
+    ) -> Result<APIResult<PlayUrlInfo>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?;
+        let resp = self
+            .client
+            .get(format!(
+                "https://api.live.bilibili.com/xlive/web-room/v2/index/getRoomPlayInfo?room_id={room_id}&protocol=0,1&format=0,1,2&codec=0,1&qn={qn}&platform=web&ptype=8"
+            ))
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<PlayUrlInfo>>().await
+    }
 }
 
 ///
 /// # Example
 /// ```no_run
+/// # use bilili_rs::api::LoginManager;
+/// # async fn run() {
 /// let login_manager = LoginManager::create(3);
-/// let (login_url,api_client_rx) = login_manager.get_one_login_url().await.unwrap();
+/// let (login_url, mut api_client_rx) = login_manager.get_one_login_url().await.unwrap();
 /// // 让用户扫描 login_url.url 的二维码
 /// let api_client = api_client_rx.recv().await.unwrap();
-/// // save(client.cookies.join("\n"))
+/// // save(api_client.cookies.join("\n"))
+/// # }
 /// ```
 
 #[derive(Debug, Clone)]
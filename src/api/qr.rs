@@ -0,0 +1,36 @@
+use std::io::Cursor;
+
+use image::{ImageFormat, Luma};
+use qrcode::{render::svg, QrCode};
+
+use super::LoginUrl;
+
+#[derive(thiserror::Error, Debug)]
+pub enum QrError {
+    #[error("QrCodeError {0}")]
+    QrCodeError(#[from] qrcode::types::QrError),
+    #[error("ImageError {0}")]
+    ImageError(#[from] image::ImageError),
+}
+
+impl LoginUrl {
+    /// 把登录链接渲染成 png 图片，`size` 为图片的最小边长（像素）
+    pub fn render_qr_png(&self, size: u32) -> Result<Vec<u8>, QrError> {
+        let code = QrCode::new(self.url.as_bytes())?;
+        let img = code.render::<Luma<u8>>().min_dimensions(size, size).build();
+        let mut buf = Cursor::new(Vec::new());
+        img.write_to(&mut buf, ImageFormat::Png)?;
+        Ok(buf.into_inner())
+    }
+
+    /// 把登录链接渲染成 svg
+    pub fn render_qr_svg(&self) -> Result<String, QrError> {
+        let code = QrCode::new(self.url.as_bytes())?;
+        Ok(code
+            .render()
+            .min_dimensions(200, 200)
+            .dark_color(svg::Color("#000000"))
+            .light_color(svg::Color("#ffffff"))
+            .build())
+    }
+}
@@ -0,0 +1,72 @@
+use reqwest::{Method, RequestBuilder, Response};
+use tokio::time::Duration;
+
+use super::APIClient;
+
+/// http 请求失败后的重试策略，见 `APIClientBuilder::retries`
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// 失败后最多重试的次数，0 表示不重试
+    pub retries: u32,
+    /// 第 n 次重试前等待 `backoff * 2 ^ (n - 1)`
+    pub backoff: Duration,
+    /// 是否重试 POST 请求，送礼、发弹幕这类接口重试可能会重复执行，默认不重试
+    pub retry_post: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            backoff: Duration::from_millis(500),
+            retry_post: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+    }
+}
+
+/// 代替 `RequestBuilder::send`，按 `APIClient` 的 `RetryPolicy` 重试
+///
+/// 只有超时、连接失败和 5xx 会重试，接口返回的 `code` 不为 0 时不会重试
+pub(crate) trait SendRetry {
+    async fn send_retry(self, api_client: &APIClient) -> Result<Response, reqwest::Error>;
+}
+
+impl SendRetry for RequestBuilder {
+    async fn send_retry(self, api_client: &APIClient) -> Result<Response, reqwest::Error> {
+        let policy = api_client.retry;
+        let (client, req) = self.build_split();
+        let req = req?;
+        let retryable = req.method() == Method::GET || policy.retry_post;
+
+        let mut attempt = 0;
+        loop {
+            // body 是流的时候无法 clone，这时不重试
+            let next = if retryable && attempt < policy.retries {
+                req.try_clone()
+            } else {
+                None
+            };
+            let Some(next) = next else {
+                return client.execute(req).await;
+            };
+
+            match client.execute(next).await {
+                Ok(resp) if !resp.status().is_server_error() => return Ok(resp),
+                Ok(resp) => log::warn!("{} {} status {}", req.method(), req.url(), resp.status()),
+                Err(e) if e.is_timeout() || e.is_connect() => {
+                    log::warn!("{} {} error {}", req.method(), req.url(), e)
+                }
+                Err(e) => return Err(e),
+            }
+            attempt += 1;
+            tokio::time::sleep(policy.delay(attempt)).await;
+        }
+    }
+}
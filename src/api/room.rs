@@ -0,0 +1,315 @@
+use std::{collections::HashMap, num::ParseIntError, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult};
+
+/// 直播间号，接口都使用真实房间号，短号会通过 `room_init` 查询一次后缓存下来
+///
+/// 从 `u64` 转换时不知道是不是短号，使用时同样会查询一次
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoomId {
+    Real(u64),
+    Unknown(u64),
+}
+
+impl RoomId {
+    pub fn id(&self) -> u64 {
+        match self {
+            RoomId::Real(id) | RoomId::Unknown(id) => *id,
+        }
+    }
+}
+
+impl From<u64> for RoomId {
+    fn from(id: u64) -> Self {
+        RoomId::Unknown(id)
+    }
+}
+
+impl From<u32> for RoomId {
+    fn from(id: u32) -> Self {
+        RoomId::Unknown(id as u64)
+    }
+}
+
+impl FromStr for RoomId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(RoomId::Unknown(s.trim().parse()?))
+    }
+}
+
+impl std::fmt::Display for RoomId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct RoomInit {
+    /// 真实房间号
+    #[serde(default)]
+    pub room_id: u64,
+    /// 短号，没有时为 0
+    #[serde(default)]
+    pub short_id: u64,
+    #[serde(default)]
+    pub uid: u64,
+    /// 0 关播, 1 直播, 2 轮播
+    #[serde(default)]
+    pub live_status: u8,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GuardUser {
+    pub uid: u64,
+    #[serde(default)]
+    pub username: String,
+    /// 1总督 2提督 3舰长
+    #[serde(default)]
+    pub guard_level: u32,
+    #[serde(default)]
+    pub rank: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct GuardListInfo {
+    /// 大航海总人数
+    #[serde(default)]
+    pub num: u32,
+    /// 总页数
+    #[serde(default)]
+    pub page: u32,
+    /// 当前页
+    #[serde(default)]
+    pub now: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GuardList {
+    #[serde(default)]
+    pub info: GuardListInfo,
+    /// 只在第一页返回
+    #[serde(default)]
+    pub top3: Vec<GuardUser>,
+    #[serde(default)]
+    pub list: Vec<GuardUser>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct RoomStatus {
+    #[serde(default)]
+    pub uid: u64,
+    #[serde(default)]
+    pub room_id: u64,
+    /// 0 未开播 1 直播中 2 轮播中
+    #[serde(default)]
+    pub live_status: u8,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    #[serde(alias = "cover_from_user")]
+    pub cover: String,
+    #[serde(default)]
+    pub uname: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct HistoryDanmu {
+    #[serde(default)]
+    pub uid: u64,
+    #[serde(default)]
+    pub nickname: String,
+    #[serde(default)]
+    pub text: String,
+    /// 发送时间，比如 "2024-01-01 20:00:00"
+    #[serde(default)]
+    pub timeline: String,
+}
+
+#[derive(Deserialize)]
+struct RawHistoryDanmu {
+    #[serde(default)]
+    room: Vec<HistoryDanmu>,
+}
+
+/// websocket 的 `ONLINE_RANK_V2` 和 http 的 `get_online_gold_rank` 共用
+#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+pub struct OnlineUser {
+    #[serde(default)]
+    pub guard_level: u32,
+    #[serde(default)]
+    #[serde(alias = "userRank")]
+    pub rank: usize,
+    #[serde(default)]
+    pub uid: u64,
+    #[serde(default)]
+    #[serde(alias = "name")]
+    pub uname: String,
+}
+
+/// 高能榜
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct GoldRank {
+    /// 高能用户总数
+    #[serde(default)]
+    #[serde(rename = "onlineNum")]
+    pub online_num: u64,
+    #[serde(default)]
+    #[serde(rename = "OnlineRankItem")]
+    pub list: Vec<OnlineUser>,
+}
+
+impl APIClient {
+    /// 查询直播间的真实房间号，`room_id` 可以是短号
+    pub async fn room_init(&self, room_id: u64) -> Result<APIResult<RoomInit>, reqwest::Error> {
+        let resp = self
+            .client
+            .get(format!(
+                "https://api.live.bilibili.com/room/v1/Room/room_init?id={room_id}"
+            ))
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<RoomInit>>().await
+    }
+
+    /// 得到真实房间号，查询结果在 clone 出来的 client 之间共享
+    ///
+    /// 直播间不存在时原样返回
+    pub async fn resolve_room_id(&self, room_id: impl Into<RoomId>) -> Result<u64, reqwest::Error> {
+        let id = match room_id.into() {
+            RoomId::Real(id) => return Ok(id),
+            RoomId::Unknown(id) => id,
+        };
+        if let Some(real) = self.room_ids.lock().unwrap().get(&id) {
+            return Ok(*real);
+        }
+        match self.room_init(id).await?.data {
+            Some(info) if info.room_id > 0 => {
+                self.room_ids.lock().unwrap().insert(id, info.room_id);
+                Ok(info.room_id)
+            }
+            _ => Ok(id),
+        }
+    }
+
+    /// 已经知道真实房间号时直接写入缓存，之后 `resolve_room_id` 不再查询
+    pub fn set_real_room_id(&self, room_id: u64, real_room_id: u64) {
+        self.room_ids.lock().unwrap().insert(room_id, real_room_id);
+    }
+
+    /// 批量查询主播的直播间状态，返回 uid 到直播间的映射，没有直播间的 uid 不会出现在结果里
+    pub async fn get_status_info_by_uids(
+        &self,
+        uids: &[u64],
+    ) -> Result<APIResult<HashMap<u64, RoomStatus>>, reqwest::Error> {
+        let resp = self
+            .client
+            .post("https://api.live.bilibili.com/room/v1/Room/get_status_info_by_uids")
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .json(&serde_json::json!({ "uids": uids }))
+            .send_retry(self)
+            .await?;
+
+        let APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data,
+        } = resp.json::<APIResult<serde_json::Value>>().await?;
+        // 没有查到任何直播间时接口返回的是空数组而不是空对象
+        let data = data.map(|data| {
+            if !data.is_object() {
+                return HashMap::new();
+            }
+            serde_json::from_value(data).unwrap_or_else(|e| {
+                log::warn!("get_status_info_by_uids parse error {}", e);
+                HashMap::new()
+            })
+        });
+        Ok(APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data,
+        })
+    }
+
+    /// 获取直播间大航海列表，`ruid` 为主播的 uid，`page` 从 1 开始
+    pub async fn get_guard_list(
+        &self,
+        room_id: impl Into<RoomId>,
+        ruid: u64,
+        page: u32,
+    ) -> Result<APIResult<GuardList>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?;
+        let resp = self
+            .client
+            .get(format!(
+                "https://api.live.bilibili.com/xlive/app-room/v2/guardTab/topList?roomid={room_id}&ruid={ruid}&page={page}&page_size=20"
+            ))
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<GuardList>>().await
+    }
+
+    /// 获取直播间当前的高能榜，`ruid` 为主播的 uid，`page` 从 1 开始
+    pub async fn get_online_gold_rank(
+        &self,
+        ruid: u64,
+        room_id: impl Into<RoomId>,
+        page: u32,
+    ) -> Result<APIResult<GoldRank>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?;
+        let resp = self
+            .client
+            .get(format!(
+                "https://api.live.bilibili.com/xlive/general-interface/v1/rank/getOnlineGoldRank?ruid={ruid}&roomId={room_id}&page={page}&pageSize=50"
+            ))
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<GoldRank>>().await
+    }
+
+    /// 获取直播间最近的弹幕，按时间从旧到新排列
+    pub async fn get_history_danmaku(
+        &self,
+        room_id: impl Into<RoomId>,
+    ) -> Result<APIResult<Vec<HistoryDanmu>>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?;
+        let resp = self
+            .client
+            .get(format!(
+                "https://api.live.bilibili.com/xlive/web-room/v1/dM/gethistory?roomid={room_id}"
+            ))
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        let APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data,
+        } = resp.json::<APIResult<RawHistoryDanmu>>().await?;
+        Ok(APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data: data.map(|data| data.room),
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SearchResult<T> {
+    #[serde(default)]
+    pub page: u32,
+    #[serde(default)]
+    #[serde(rename = "numPages")]
+    pub num_pages: u32,
+    #[serde(default)]
+    #[serde(rename = "numResults")]
+    pub num_results: u32,
+    #[serde(default = "Vec::new")]
+    pub result: Vec<T>,
+}
+
+pub type LiveSearchResult = SearchResult<LiveRoomItem>;
+pub type UserSearchResult = SearchResult<UserItem>;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct LiveRoomItem {
+    #[serde(default)]
+    pub roomid: u64,
+    #[serde(default)]
+    pub uid: u64,
+    /// 匹配的关键字会被 `<em class="keyword">` 包起来
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub uname: String,
+    #[serde(default)]
+    pub online: u64,
+    #[serde(default)]
+    pub live_status: u8,
+    #[serde(default)]
+    pub cover: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct UserItem {
+    #[serde(default)]
+    pub mid: u64,
+    #[serde(default)]
+    pub uname: String,
+    #[serde(default)]
+    pub usign: String,
+    #[serde(default)]
+    pub fans: u64,
+    /// 没有直播间时为 0
+    #[serde(default)]
+    pub room_id: u64,
+    #[serde(default)]
+    pub is_live: u8,
+}
+
+impl APIClient {
+    /// 搜索直播间，`page` 从 1 开始
+    ///
+    /// cookie 里没有 `buvid3` 时会被拦截（`code: -412`），可以先调用 `ensure_buvid`
+    pub async fn search_live(
+        &self,
+        keyword: &str,
+        page: u32,
+    ) -> Result<APIResult<LiveSearchResult>, reqwest::Error> {
+        self.search_by_type("live_room", keyword, page).await
+    }
+
+    /// 搜索用户，`page` 从 1 开始
+    pub async fn search_user(
+        &self,
+        keyword: &str,
+        page: u32,
+    ) -> Result<APIResult<UserSearchResult>, reqwest::Error> {
+        self.search_by_type("bili_user", keyword, page).await
+    }
+
+    async fn search_by_type<T: serde::de::DeserializeOwned>(
+        &self,
+        search_type: &str,
+        keyword: &str,
+        page: u32,
+    ) -> Result<APIResult<SearchResult<T>>, reqwest::Error> {
+        let page = page.to_string();
+        let resp = self
+            .client
+            .get("https://api.bilibili.com/x/web-interface/search/type")
+            .headers(self.default_headers("https://search.bilibili.com"))
+            .query(&[
+                ("search_type", search_type),
+                ("keyword", keyword),
+                ("page", page.as_str()),
+            ])
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<SearchResult<T>>>().await
+    }
+}
@@ -0,0 +1,357 @@
+use std::{collections::BTreeMap, path::Path, sync::Arc};
+
+use reqwest::cookie::Jar;
+
+use serde::{Deserialize, Serialize};
+
+use super::retry::SendRetry;
+use super::{merge_cookie_list, APIClient, APIResult, AppToken, CheckCookieError, UserToken};
+
+/// 保存到本地的登录信息
+///
+/// # Example
+///
+/// ```no_run
+/// # use bilili_rs::api::APIClient;
+/// # fn run(client: APIClient) {
+/// client.save_session("session.json").unwrap();
+/// let client = APIClient::load_session("session.json").unwrap();
+/// # }
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Session {
+    pub cookies: Vec<String>,
+    #[serde(default)]
+    pub refresh_token: String,
+    /// 保存时间，unix 秒
+    #[serde(default)]
+    pub saved_at: u64,
+    #[serde(default)]
+    pub app_token: Option<AppToken>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SessionError {
+    #[error("IO: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("json error {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("cookie error {0}")]
+    CookieError(#[from] CheckCookieError),
+    #[error("HttpError {0}")]
+    HttpError(#[from] reqwest::Error),
+    #[error("profile not found: {0}")]
+    ProfileNotFound(String),
+}
+
+/// 浏览器插件导出的 `cookies.txt` 的第一行
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File";
+
+/// 解析 Netscape 格式的 `cookies.txt`，只保留 `bilibili.com` 的 cookie
+///
+/// 每行用 tab 分隔：域名、是否包含子域名、路径、是否只用于 https、过期时间、名字、值，
+/// `#HttpOnly_` 开头的行也是 cookie，其他 `#` 开头的行是注释
+pub fn parse_netscape_cookies(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_end_matches(['\r', '\n']);
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.starts_with('#') || line.trim().is_empty() {
+                return None;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [domain, _, path, secure, _, name, value] = fields[..] else {
+                log::debug!("bad netscape cookie line: {}", line);
+                return None;
+            };
+            if !(domain == "bilibili.com" || domain.ends_with(".bilibili.com")) {
+                return None;
+            }
+            let mut cookie = format!("{name}={value}; Domain={domain}; Path={path}");
+            if secure.eq_ignore_ascii_case("TRUE") {
+                cookie.push_str("; Secure");
+            }
+            Some(cookie)
+        })
+        .collect()
+}
+
+impl UserToken {
+    /// 读取浏览器导出的 Netscape 格式的 `cookies.txt`，见 `parse_netscape_cookies`
+    ///
+    /// 构建 `APIClient` 还需要 cookie 列表，可以直接用 `APIClient::load_session`，它能识别这种格式
+    pub fn from_netscape_cookies<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<(Self, Arc<Jar>), SessionError> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(Self::create_from_tokens(&parse_netscape_cookies(&content))?)
+    }
+}
+
+impl Session {
+    /// 解析 session 内容，兼容旧的每行一个 cookie 的格式和浏览器导出的 `cookies.txt`
+    pub fn parse(content: &str) -> Result<Self, SessionError> {
+        if content.trim_start().starts_with('{') {
+            Ok(serde_json::from_str(content)?)
+        } else if content.trim_start().starts_with(NETSCAPE_HEADER) {
+            Ok(Session {
+                cookies: parse_netscape_cookies(content),
+                ..Default::default()
+            })
+        } else {
+            let cookies = content
+                .lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty())
+                .map(|l| l.to_string())
+                .collect();
+            Ok(Session {
+                cookies,
+                ..Default::default()
+            })
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SessionError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::parse(&content)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SessionError> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    pub fn into_client(self) -> Result<APIClient, SessionError> {
+        let (token, jar) = UserToken::create_from_tokens(&self.cookies)?;
+        let mut client = APIClient::new(token, jar, self.cookies)?;
+        client.refresh_token = self.refresh_token;
+        client.app_token = self.app_token;
+        Ok(client)
+    }
+}
+
+/// 账号在某个设备上的登录，见 `APIClient::get_login_sessions`
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct LoginSession {
+    /// 用于 `revoke_session`
+    #[serde(default)]
+    pub session_id: String,
+    #[serde(default, rename = "device_name")]
+    pub device: String,
+    /// 登录地点，根据 ip 得到
+    #[serde(default)]
+    pub location: String,
+    /// 最后活跃时间，unix 秒
+    #[serde(default, rename = "login_time")]
+    pub last_active: u64,
+    /// 是否是当前使用的这个登录
+    #[serde(default, rename = "is_current")]
+    pub current: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct LoginSessionList {
+    #[serde(default)]
+    list: Vec<LoginSession>,
+}
+
+/// 多个账号的登录信息，key 为账号名
+///
+/// 可以保存在一个 json 文件里，也可以是一个目录，目录里每个文件是一个账号的 `Session`，
+/// 文件名（去掉扩展名）为账号名
+///
+/// # Example
+///
+/// ```no_run
+/// # use bilili_rs::api::Profiles;
+/// let profiles = Profiles::load("accounts.json").unwrap();
+/// for name in profiles.names() {
+///     println!("{name}");
+/// }
+/// let client = profiles.client("main").unwrap();
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct Profiles {
+    pub profiles: BTreeMap<String, Session>,
+}
+
+impl Profiles {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SessionError> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            let content = std::fs::read_to_string(path)?;
+            return Ok(serde_json::from_str(&content)?);
+        }
+
+        let mut profiles = BTreeMap::new();
+        for entry in std::fs::read_dir(path)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            match Session::load(&path) {
+                Ok(session) => {
+                    profiles.insert(name.to_string(), session);
+                }
+                Err(e) => log::warn!("load profile {} error: {}", path.display(), e),
+            }
+        }
+        Ok(Profiles { profiles })
+    }
+
+    /// 保存为一个 json 文件
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SessionError> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(|k| k.as_str())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Session> {
+        self.profiles.get(name)
+    }
+
+    /// 添加或者替换一个账号
+    pub fn insert(&mut self, name: impl Into<String>, session: Session) {
+        self.profiles.insert(name.into(), session);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Session> {
+        self.profiles.remove(name)
+    }
+
+    /// 用账号 `name` 的登录信息构建客户端，不存在时返回 `SessionError::ProfileNotFound`
+    pub fn client(&self, name: &str) -> Result<APIClient, SessionError> {
+        self.get(name)
+            .cloned()
+            .ok_or_else(|| SessionError::ProfileNotFound(name.to_string()))?
+            .into_client()
+    }
+}
+
+impl APIClient {
+    pub fn session(&self) -> Session {
+        let saved_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs();
+        let mut cookies = self.cookies.clone();
+        merge_cookie_list(&mut cookies, &self.buvid.lock().unwrap().cookies);
+        Session {
+            cookies,
+            refresh_token: self.refresh_token.clone(),
+            saved_at,
+            app_token: self.app_token.clone(),
+        }
+    }
+
+    /// 以 json 格式保存登录信息
+    pub fn save_session<P: AsRef<Path>>(&self, path: P) -> Result<(), SessionError> {
+        self.session().save(path)
+    }
+
+    /// 从 cookie 字符串构建客户端，用于不方便写文件的场景，比如从环境变量读取
+    ///
+    /// 有多行时每行一个 cookie（可以带 `Path` 之类的属性），
+    /// 否则按浏览器请求头 `Cookie` 的格式用 `;` 分割，如 `SESSDATA=xxx; bili_jct=xxx; DedeUserID=xxx`
+    pub fn from_cookie_str(cookies: &str) -> Result<Self, SessionError> {
+        let cookies: Vec<String> = if cookies.trim().contains('\n') {
+            cookies.lines().map(|l| l.trim().to_string()).collect()
+        } else {
+            cookies.split(';').map(|c| c.trim().to_string()).collect()
+        };
+        let cookies = cookies.into_iter().filter(|c| !c.is_empty()).collect();
+        Session {
+            cookies,
+            ..Default::default()
+        }
+        .into_client()
+    }
+
+    /// 读取 `save_session` 保存的登录信息，也可以读取旧的每行一个 cookie 的文件和 `cookies.txt`
+    pub fn load_session<P: AsRef<Path>>(path: P) -> Result<Self, SessionError> {
+        Session::load(path)?.into_client()
+    }
+
+    /// 读取 `Profiles` 中账号 `name` 的登录信息，`path` 可以是文件或者目录
+    pub fn load_profile<P: AsRef<Path>>(path: P, name: &str) -> Result<Self, SessionError> {
+        Profiles::load(path)?.client(name)
+    }
+
+    /// 同 `load_session`，并且检查登录是否已经失效，失效时返回 `CheckCookieError::Expired`
+    ///
+    /// 旧的登录信息里没有 `buvid3` 时会顺便补上，见 `ensure_buvid`
+    pub async fn load_session_checked<P: AsRef<Path>>(path: P) -> Result<Self, SessionError> {
+        let client = Self::load_session(path)?;
+        if client.check_login().await? {
+            client.ensure_buvid().await?;
+            Ok(client)
+        } else {
+            Err(CheckCookieError::Expired.into())
+        }
+    }
+
+    /// 退出登录，成功后当前的 cookie 在服务端失效，本地保存的登录信息需要调用方自行删除
+    pub async fn logout(&self) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let param = [("biliCSRF", self.token.csrf.as_str())];
+        let resp = self
+            .client
+            .post("https://passport.bilibili.com/login/exit/v2")
+            .headers(self.default_headers("https://www.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+
+    /// 列出账号当前在哪些设备上登录
+    pub async fn get_login_sessions(&self) -> Result<APIResult<Vec<LoginSession>>, reqwest::Error> {
+        let resp = self
+            .client
+            .get("https://passport.bilibili.com/x/safecenter/user/center/web_line")
+            .headers(self.default_headers("https://passport.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        let r = resp.json::<APIResult<LoginSessionList>>().await?;
+        Ok(APIResult {
+            code: r.code,
+            message: r.message,
+            ttl: r.ttl,
+            ts: r.ts,
+            data: r.data.map(|data| data.list),
+        })
+    }
+
+    /// 让另一台设备上的登录失效，`session_id` 见 `LoginSession::session_id`
+    pub async fn revoke_session(
+        &self,
+        session_id: &str,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let param = [
+            ("session_id", session_id),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let resp = self
+            .client
+            .post("https://passport.bilibili.com/x/safecenter/user/center/web_line/kick")
+            .headers(self.default_headers("https://passport.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+}
@@ -0,0 +1,72 @@
+use tokio::time::Duration;
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult, ApiError, RoomId};
+
+/// 两次分享之间的间隔，太快会被忽略
+const SHARE_INTERVAL: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Default)]
+pub struct ShareResult {
+    /// 成功的次数
+    pub succeeded: u32,
+    /// 最后一次成功分享时接口返回的 `data`，服务端返回了剩余次数之类的信息时可以从这里取
+    pub last_data: Option<serde_json::Value>,
+    /// 中途停止时接口返回的错误
+    pub error: Option<ApiError>,
+}
+
+impl APIClient {
+    /// 分享直播间一次
+    pub async fn share_room(
+        &self,
+        room_id: impl Into<RoomId>,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?.to_string();
+        let param = [
+            ("roomid", room_id.as_str()),
+            ("interact_type", "3"),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let resp = self
+            .client
+            .post("https://api.live.bilibili.com/xlive/app-room/v1/index/TrigerInteract")
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+
+    /// 重复分享 `count` 次，用于完成每日分享任务
+    ///
+    /// 某次分享返回非 0 的 `code` 时停止，返回成功的次数
+    pub async fn share_room_times(
+        &self,
+        room_id: impl Into<RoomId>,
+        count: u32,
+    ) -> Result<ShareResult, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?;
+        let mut result = ShareResult::default();
+        for i in 0..count {
+            if i > 0 {
+                tokio::time::sleep(SHARE_INTERVAL).await;
+            }
+            let r = self.share_room(room_id).await?;
+            match r.check() {
+                Ok(data) => {
+                    result.succeeded += 1;
+                    result.last_data = data;
+                }
+                Err(e) => {
+                    log::warn!("[{room_id}] share room error {}", e);
+                    result.error = Some(e);
+                    break;
+                }
+            }
+        }
+        Ok(result)
+    }
+}
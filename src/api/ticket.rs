@@ -0,0 +1,132 @@
+use std::sync::atomic::Ordering;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult, BILI_URL};
+
+const TICKET_HMAC_KEY: &[u8] = b"XgwSnGZ1p";
+const TICKET_KEY_ID: &str = "ec02";
+/// 过期前多久开始刷新
+const TICKET_REFRESH_MARGIN: u64 = 60 * 60;
+/// 获取失败后多久再重试，避免每个请求都去请求一次 ticket
+const TICKET_RETRY_BACKOFF: u64 = 60;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct BiliTicket {
+    #[serde(default)]
+    pub ticket: String,
+    /// 签发时间，unix 秒
+    #[serde(default)]
+    pub created_at: u64,
+    /// 有效期，秒
+    #[serde(default)]
+    pub ttl: u64,
+}
+
+impl BiliTicket {
+    pub fn expires_at(&self) -> u64 {
+        self.created_at + self.ttl
+    }
+}
+
+fn hmac_sha256_hex(key: &[u8], message: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(message.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+impl APIClient {
+    /// 重新获取 `bili_ticket` 并写入 cookie
+    ///
+    /// 部分直播接口（比如发弹幕）缺少这个 cookie 时会被风控，
+    /// 一般不需要手动调用，`send_barrage` 会在过期前自动刷新。
+    pub async fn refresh_bili_ticket(&self) -> Result<APIResult<BiliTicket>, reqwest::Error> {
+        let ts = now_secs().to_string();
+        let hexsign = hmac_sha256_hex(TICKET_HMAC_KEY, &format!("ts{}", ts));
+        let query = [
+            ("key_id", TICKET_KEY_ID),
+            ("hexsign", hexsign.as_str()),
+            ("context[ts]", ts.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let r = async {
+            self.client
+                .post("https://api.bilibili.com/bapis/bilibili.api.ticket.v1.Ticket/GenWebTicket")
+                .headers(self.default_headers("https://www.bilibili.com"))
+                .query(&query)
+                .send_retry(self)
+                .await?
+                .json::<APIResult<BiliTicket>>()
+                .await
+        }
+        .await;
+        let r = match r {
+            Ok(r) => r,
+            Err(e) => {
+                self.ticket_backoff();
+                return Err(e);
+            }
+        };
+
+        match &r.data {
+            Some(ticket) if r.code == 0 => {
+                let domain_url = BILI_URL.parse().unwrap();
+                let expires_at = ticket.expires_at();
+                self.jar.add_cookie_str(
+                    &format!(
+                        "bili_ticket={}; Domain=.bilibili.com; Path=/",
+                        ticket.ticket
+                    ),
+                    &domain_url,
+                );
+                self.jar.add_cookie_str(
+                    &format!(
+                        "bili_ticket_expires={}; Domain=.bilibili.com; Path=/",
+                        expires_at
+                    ),
+                    &domain_url,
+                );
+                self.ticket_expires_at.store(expires_at, Ordering::Relaxed);
+            }
+            _ => {
+                log::warn!(
+                    "get bili_ticket error code: {}, message: {:?}",
+                    r.code,
+                    r.message
+                );
+                self.ticket_backoff();
+            }
+        }
+        Ok(r)
+    }
+
+    /// 获取失败时记录一个假的过期时间，`TICKET_RETRY_BACKOFF` 秒内 `ensure_bili_ticket` 不再刷新
+    fn ticket_backoff(&self) {
+        let retry_at = now_secs() + TICKET_REFRESH_MARGIN + TICKET_RETRY_BACKOFF;
+        self.ticket_expires_at.store(retry_at, Ordering::Relaxed);
+    }
+
+    /// `bili_ticket` 不存在或快过期时刷新，否则什么都不做
+    pub async fn ensure_bili_ticket(&self) -> Result<(), reqwest::Error> {
+        let expires_at = self.ticket_expires_at.load(Ordering::Relaxed);
+        if now_secs() + TICKET_REFRESH_MARGIN < expires_at {
+            return Ok(());
+        }
+        self.refresh_bili_ticket().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+//! TV 端扫码登录，登录后除了 web 的 cookie 还会得到 app 接口使用的 `access_token`
+
+use reqwest::{cookie::Jar, Client};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use super::{
+    APIClient, APIClientBuilder, APIResult, LoginError, LoginStep, PollOptions, QrResultError,
+    UserToken,
+};
+
+/// 云视听小电视的 appkey
+const APP_KEY: &str = "4409e2ce8ffd12b8";
+const APP_SEC: &str = "59b43e04ad6965f34319062b478f83dd";
+
+/// app 接口的签名：参数按 key 排序后编码，拼接 appsec 后求 md5
+pub(crate) fn app_sign(params: &[(&str, &str)], app_sec: &str) -> String {
+    let mut params = params.to_vec();
+    params.sort();
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish();
+    format!("{:x}", md5::compute(format!("{query}{app_sec}")))
+}
+
+/// 加上 `appkey`、`ts` 和 `sign`
+fn signed_form(params: &[(&str, &str)]) -> Vec<(String, String)> {
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+        .to_string();
+    let mut params = params.to_vec();
+    params.push(("appkey", APP_KEY));
+    params.push(("ts", ts.as_str()));
+    let sign = app_sign(&params, APP_SEC);
+    let mut form: Vec<(String, String)> = params
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    form.push(("sign".to_string(), sign));
+    form
+}
+
+/// app 接口使用的登录凭证
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct AppToken {
+    #[serde(default)]
+    pub mid: u64,
+    #[serde(default)]
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: String,
+    /// 过期时间，unix 秒
+    #[serde(default)]
+    pub expires_at: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TvLogin {
+    pub url: String,
+    pub auth_code: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct TvLoginResult {
+    #[serde(default)]
+    mid: u64,
+    #[serde(default)]
+    access_token: String,
+    #[serde(default)]
+    refresh_token: String,
+    /// 秒
+    #[serde(default)]
+    expires_in: u64,
+    #[serde(default)]
+    cookie_info: TvCookieInfo,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct TvCookieInfo {
+    #[serde(default)]
+    cookies: Vec<TvCookie>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct TvCookie {
+    name: String,
+    value: String,
+}
+
+impl TvLogin {
+    pub async fn get_login_url() -> Result<APIResult<Self>, reqwest::Error> {
+        Self::get_login_url_with(&APIClient::builder()).await
+    }
+
+    /// 使用 `builder` 中的代理、超时和 UA 获取登录二维码，`url` 即二维码的内容
+    pub async fn get_login_url_with(
+        builder: &APIClientBuilder,
+    ) -> Result<APIResult<Self>, reqwest::Error> {
+        let client = builder.build_http_client(Arc::new(Jar::default()))?;
+        let resp = client
+            .post("https://passport.bilibili.com/x/passport-tv-login/qrcode/auth_code")
+            .headers(builder.default_headers("https://passport.bilibili.com"))
+            .form(&signed_form(&[("local_id", "0")]))
+            .send()
+            .await?;
+        resp.json::<APIResult<TvLogin>>().await
+    }
+
+    pub async fn poll_tokens(&self) -> Result<APIResult<APIClient>, LoginError> {
+        self.poll_tokens_with(APIClient::builder(), PollOptions::default())
+            .await
+    }
+
+    /// 同 `LoginUrl::poll_tokens_with`，登录成功后 `APIClient::app_token` 里保存了 `access_token`
+    pub async fn poll_tokens_with(
+        &self,
+        builder: APIClientBuilder,
+        poll: PollOptions,
+    ) -> Result<APIResult<APIClient>, LoginError> {
+        self.poll_tokens_until(builder, poll, &CancellationToken::new())
+            .await
+    }
+
+    /// 同 `poll_tokens`，`cancel` 被取消后在下一次查询之前返回 `LoginError::Cancelled`
+    pub async fn poll_tokens_with_cancel(
+        &self,
+        cancel: CancellationToken,
+    ) -> Result<APIResult<APIClient>, LoginError> {
+        self.poll_tokens_until(APIClient::builder(), PollOptions::default(), &cancel)
+            .await
+    }
+
+    async fn poll_tokens_until(
+        &self,
+        builder: APIClientBuilder,
+        poll: PollOptions,
+        cancel: &CancellationToken,
+    ) -> Result<APIResult<APIClient>, LoginError> {
+        let client = builder.build_http_client(Arc::new(Jar::default()))?;
+
+        tokio::time::timeout(poll.timeout, async {
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(poll.interval) => {}
+                    _ = cancel.cancelled() => return Err(LoginError::Cancelled),
+                }
+                let r = self.poll_step(&client, &builder).await?;
+                let data = match r.data {
+                    Some(LoginStep::NotScanned) | Some(LoginStep::Scanned) => continue,
+                    Some(LoginStep::Expired) => return Err(QrResultError::QrExpired.into()),
+                    Some(LoginStep::Confirmed(api_client)) => Some(*api_client),
+                    None => None,
+                };
+                return Ok(APIResult {
+                    code: r.code,
+                    message: r.message,
+                    ttl: r.ttl,
+                    ts: r.ts,
+                    data,
+                });
+            }
+        })
+        .await
+        .unwrap_or(Err(QrResultError::QrExpired.into()))
+    }
+
+    /// 查询一次扫码结果，不会等待，见 `LoginUrl::poll_once`
+    pub async fn poll_once(&self, client: &Client) -> Result<LoginStep, QrResultError> {
+        let r = self.poll_step(client, &APIClient::builder()).await?;
+        r.data.ok_or(QrResultError::UnknownError {
+            code: r.code,
+            message: r.message.unwrap_or_default(),
+        })
+    }
+
+    /// 接口本身返回错误时 `data` 为 `None`
+    async fn poll_step(
+        &self,
+        client: &Client,
+        builder: &APIClientBuilder,
+    ) -> Result<APIResult<LoginStep>, QrResultError> {
+        let resp = client
+            .post("https://passport.bilibili.com/x/passport-tv-login/qrcode/poll")
+            .headers(builder.default_headers("https://passport.bilibili.com"))
+            .form(&signed_form(&[
+                ("auth_code", self.auth_code.as_str()),
+                ("local_id", "0"),
+            ]))
+            .send()
+            .await?;
+        let APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data,
+        } = resp.json::<APIResult<TvLoginResult>>().await?;
+
+        let step = match (code, data) {
+            (0, Some(r)) => {
+                let cookies: Vec<String> = r
+                    .cookie_info
+                    .cookies
+                    .iter()
+                    .map(|c| format!("{}={}; Domain=.bilibili.com; Path=/", c.name, c.value))
+                    .collect();
+                let (token, jar) = UserToken::create_from_tokens(&cookies)?;
+                let mut api_client = builder.clone().build(token, jar, cookies)?;
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .expect("Time went backwards")
+                    .as_secs();
+                api_client.app_token = Some(AppToken {
+                    mid: r.mid,
+                    access_token: r.access_token,
+                    refresh_token: r.refresh_token,
+                    expires_at: now + r.expires_in,
+                });
+                if let Err(e) = api_client.ensure_buvid().await {
+                    log::warn!("ensure_buvid error: {}", e);
+                }
+                Some(LoginStep::Confirmed(Box::new(api_client)))
+            }
+            // 86039 未扫码，86090 已扫码未确认，86038 二维码已失效
+            (86039, _) => Some(LoginStep::NotScanned),
+            (86090, _) => Some(LoginStep::Scanned),
+            (86038, _) => Some(LoginStep::Expired),
+            _ => None,
+        };
+
+        Ok(APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data: step,
+        })
+    }
+}
@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult, RoomStatus};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelationAction {
+    Follow,
+    Unfollow,
+}
+
+impl RelationAction {
+    fn act(&self) -> &'static str {
+        match self {
+            RelationAction::Follow => "1",
+            RelationAction::Unfollow => "2",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct WbiImg {
+    #[serde(default)]
+    pub img_url: String,
+    #[serde(default)]
+    pub sub_url: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct NavInfo {
+    #[serde(default)]
+    #[serde(rename = "isLogin")]
+    pub is_login: bool,
+    #[serde(default)]
+    pub mid: u64,
+    #[serde(default)]
+    pub uname: String,
+    /// 硬币数
+    #[serde(default)]
+    pub money: f64,
+    /// 0 无 1 有
+    #[serde(default)]
+    #[serde(rename = "vipStatus")]
+    pub vip_status: u32,
+    /// wbi 签名用的 key
+    #[serde(default)]
+    pub wbi_img: WbiImg,
+}
+
+/// 用户名片，见 `APIClient::get_user_info`
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct UserInfo {
+    #[serde(default)]
+    pub mid: u64,
+    #[serde(default)]
+    pub name: String,
+    /// "男" / "女" / "保密"
+    #[serde(default)]
+    pub sex: String,
+    /// 头像地址
+    #[serde(default)]
+    pub face: String,
+    /// 签名
+    #[serde(default)]
+    pub sign: String,
+    #[serde(default)]
+    pub level: u32,
+    /// 关注数
+    #[serde(default)]
+    pub following: u64,
+    /// 粉丝数
+    #[serde(default)]
+    pub follower: u64,
+    /// 没有直播间时为 `None`
+    #[serde(default)]
+    pub live_room: Option<RoomStatus>,
+}
+
+impl UserInfo {
+    /// `x/web-interface/card` 的 `data`，`card.mid` 是字符串
+    fn from_card(data: &serde_json::Value) -> Self {
+        let card = &data["card"];
+        let u64_of = |v: &serde_json::Value| {
+            v.as_u64()
+                .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+                .unwrap_or_default()
+        };
+        let string_of = |key: &str| card[key].as_str().unwrap_or_default().to_string();
+        UserInfo {
+            mid: u64_of(&card["mid"]),
+            name: string_of("name"),
+            sex: string_of("sex"),
+            face: string_of("face"),
+            sign: string_of("sign"),
+            level: u64_of(&card["level_info"]["current_level"]) as u32,
+            following: u64_of(&card["attention"]),
+            follower: data["follower"]
+                .as_u64()
+                .unwrap_or_else(|| u64_of(&card["fans"])),
+            live_room: None,
+        }
+    }
+}
+
+impl APIClient {
+    /// 获取用户名片，直播间信息通过 `get_status_info_by_uids` 查询，查询失败时为 `None`
+    pub async fn get_user_info(&self, mid: u64) -> Result<APIResult<UserInfo>, reqwest::Error> {
+        let resp = self
+            .client
+            .get(format!(
+                "https://api.bilibili.com/x/web-interface/card?mid={mid}"
+            ))
+            .headers(self.default_headers("https://space.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        let APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data,
+        } = resp.json::<APIResult<serde_json::Value>>().await?;
+        let mut data = data.filter(|_| code == 0).map(|v| UserInfo::from_card(&v));
+        if let Some(info) = &mut data {
+            match self.get_status_info_by_uids(&[mid]).await {
+                Ok(r) => info.live_room = r.data.and_then(|mut rooms| rooms.remove(&mid)),
+                Err(e) => log::warn!("get live room of {mid} error {}", e),
+            }
+        }
+        Ok(APIResult {
+            code,
+            message,
+            ttl,
+            ts,
+            data,
+        })
+    }
+
+    /// 获取当前登录账号的信息
+    ///
+    /// cookie 失效时接口返回 `code: -101`，`data.is_login` 为 `false`，不会当作请求错误
+    pub async fn get_self_info(&self) -> Result<APIResult<NavInfo>, reqwest::Error> {
+        let resp = self
+            .client
+            .get("https://api.bilibili.com/x/web-interface/nav")
+            .headers(self.default_headers("https://www.bilibili.com"))
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<NavInfo>>().await
+    }
+
+    /// 检查 cookie 在服务端是否仍然有效
+    pub async fn check_login(&self) -> Result<bool, reqwest::Error> {
+        let r = self.get_self_info().await?;
+        Ok(r.data.is_some_and(|info| info.is_login))
+    }
+
+    /// 关注或取关用户
+    ///
+    /// 重复关注会返回 `code: 22014`（已经关注用户，无法重复关注），
+    /// 调用方应把它当作成功处理。
+    pub async fn modify_relation(
+        &self,
+        target_uid: u64,
+        action: RelationAction,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let fid = target_uid.to_string();
+        let param = [
+            ("fid", fid.as_str()),
+            ("act", action.act()),
+            ("re_src", "11"),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+        ];
+        let resp = self
+            .client
+            .post("https://api.bilibili.com/x/relation/modify")
+            .headers(self.default_headers("https://www.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+}
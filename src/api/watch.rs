@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tokio::{task::JoinHandle, time::Duration};
+use tokio_util::sync::CancellationToken;
+
+use super::retry::SendRetry;
+use super::{APIClient, APIResult, RoomId};
+
+const DEFAULT_HEARTBEAT_INTERVAL: u64 = 60;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct WebHeartBeat {
+    /// 服务端要求的下一次心跳间隔，秒
+    #[serde(default)]
+    pub next_interval: u64,
+}
+
+impl APIClient {
+    /// 进入直播间，网页端打开直播间时会调用一次
+    pub async fn enter_room(
+        &self,
+        room_id: impl Into<RoomId>,
+    ) -> Result<APIResult<serde_json::Value>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?.to_string();
+        let param = [
+            ("room_id", room_id.as_str()),
+            ("platform", "pc"),
+            ("csrf_token", self.token.csrf.as_str()),
+            ("csrf", self.token.csrf.as_str()),
+            ("visit_id", ""),
+        ];
+        let resp = self
+            .client
+            .post("https://api.live.bilibili.com/xlive/web-room/v1/index/roomEntryAction")
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .form(&param)
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<serde_json::Value>>().await
+    }
+
+    /// 发送一次观看心跳，`interval` 为距上一次心跳的秒数
+    pub async fn web_heartbeat(
+        &self,
+        room_id: impl Into<RoomId>,
+        interval: u64,
+    ) -> Result<APIResult<WebHeartBeat>, reqwest::Error> {
+        let room_id = self.resolve_room_id(room_id).await?;
+        let hb = base64::engine::general_purpose::STANDARD
+            .encode(format!("{}|{}|1|0", interval, room_id));
+        let resp = self
+            .client
+            .get("https://live-trace.bilibili.com/xlive/rdata-interface/v1/heartbeat/webHeartBeat")
+            .headers(self.default_headers("https://live.bilibili.com"))
+            .query(&[("hb", hb.as_str()), ("pf", "web")])
+            .send_retry(self)
+            .await?;
+
+        resp.json::<APIResult<WebHeartBeat>>().await
+    }
+}
+
+/// 进入直播间并按服务端要求的间隔持续发送观看心跳，用于计入"看过"人数
+///
+/// 网页端还会向 `x25Kn/E` 和 `x25Kn/X` 上报心跳，这两个接口需要 `id`
+/// （`[父分区, 分区, 序号, 房间号]`，序号每次加一）、`device`、`ets`、`benchmark`
+/// 以及用服务端下发的 `secret_key`/`secret_rule` 计算出的 `s` 签名，这里没有实现，
+/// 只使用不需要签名的 `webHeartBeat`。
+///
+/// 单次心跳失败只会打印日志，调用 `cancel.cancel()` 后退出。
+pub fn spawn_watch_heartbeat(
+    api_client: Arc<APIClient>,
+    room_id: u64,
+    cancel: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        match api_client.enter_room(room_id).await {
+            Ok(APIResult { code: 0, .. }) => log::debug!("[{room_id}] enter room ok"),
+            Ok(r) => log::warn!("[{room_id}] enter room code: {}, {:?}", r.code, r.message),
+            Err(e) => log::warn!("[{room_id}] enter room error {}", e),
+        }
+
+        let mut interval = DEFAULT_HEARTBEAT_INTERVAL;
+        loop {
+            match api_client.web_heartbeat(room_id, interval).await {
+                Ok(APIResult {
+                    code: 0,
+                    data: Some(hb),
+                    ..
+                }) if hb.next_interval > 0 => interval = hb.next_interval,
+                Ok(APIResult { code: 0, .. }) => {}
+                Ok(r) => log::warn!("[{room_id}] heartbeat code: {}, {:?}", r.code, r.message),
+                Err(e) => log::warn!("[{room_id}] heartbeat error {}", e),
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(interval)) => {}
+                _ = cancel.cancelled() => return,
+            }
+        }
+    })
+}
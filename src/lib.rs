@@ -1,2 +1,3 @@
 pub mod api;
+#[cfg(feature = "live_ws")]
 pub mod live_ws;
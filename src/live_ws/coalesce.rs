@@ -0,0 +1,125 @@
+use log::debug;
+use tokio::sync::mpsc::Receiver;
+use tokio::time::{Duration, Instant};
+
+use super::message::notification_msg::{NotificationMsg, OneGift};
+use super::outbox::Outbox;
+use super::{LiveConnectError, MsgStream, ServerLiveMessage};
+
+impl MsgStream {
+    /// 把同一个用户连续送出的同一种礼物合并成一条 `SEND_GIFT`
+    ///
+    /// 从收到第一个礼物开始计时，`window` 结束或收到不同的礼物时发出合并后的礼物，
+    /// `num` 和 `total_coin` 为累加值。其他消息不受影响，直接转发。
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::{sync::Arc, time::Duration};
+    /// # use bilili_rs::api::APIClient;
+    /// # async fn run(api_client: Arc<APIClient>) {
+    /// let mut stream = bilili_rs::live_ws::connect(api_client, 1234, 10)
+    ///     .coalesce(Duration::from_secs(3));
+    /// while let Some(msg) = stream.rx.recv().await { /* ... */ }
+    /// # }
+    /// ```
+    pub fn coalesce(self, window: Duration) -> MsgStream {
+        let MsgStream {
+            room_id,
+            rx,
+            channel_capacity,
+            overflow,
+            dropped,
+            cancel,
+            _cancel_guard,
+            _connect_handler,
+        } = self;
+        let (tx, new_rx) = tokio::sync::mpsc::channel(channel_capacity.max(1));
+        let outbox = Outbox::new(tx, overflow, channel_capacity, dropped.clone());
+        let _connect_handler = tokio::spawn(async move {
+            // 返回时会 drop 掉 `rx`，连接任务随后结束
+            if let Err(e) = coalesce_gifts(rx, outbox, window).await {
+                debug!("[{room_id}] coalesce stop {}", e);
+            }
+            _connect_handler.await?
+        });
+        MsgStream {
+            room_id,
+            rx: new_rx,
+            channel_capacity,
+            overflow,
+            dropped,
+            cancel,
+            _cancel_guard,
+            _connect_handler,
+        }
+    }
+}
+
+fn gift_msg(data: OneGift) -> ServerLiveMessage {
+    ServerLiveMessage::Notification(NotificationMsg::SEND_GIFT { data })
+}
+
+async fn coalesce_gifts(
+    mut rx: Receiver<ServerLiveMessage>,
+    mut outbox: Outbox,
+    window: Duration,
+) -> Result<(), LiveConnectError> {
+    let mut pending: Option<OneGift> = None;
+    let mut deadline = Instant::now();
+    loop {
+        let (msg, timeout) = tokio::select! {
+            msg = rx.recv() => (msg, false),
+            _ = tokio::time::sleep_until(deadline), if pending.is_some() => (None, true),
+            // 有积压的消息时，通道一有空位就发出去
+            permit = outbox.tx.reserve(), if !outbox.pending.is_empty() => {
+                let permit = permit.map_err(|_| LiveConnectError::TxClose)?;
+                if let Some(msg) = outbox.pending.pop_front() {
+                    permit.send(msg);
+                }
+                continue;
+            }
+        };
+        if timeout {
+            if let Some(gift) = pending.take() {
+                outbox.send(gift_msg(gift)).await?;
+            }
+            continue;
+        }
+
+        let Some(msg) = msg else {
+            if let Some(gift) = pending.take() {
+                outbox.send(gift_msg(gift)).await?;
+            }
+            return outbox.flush().await;
+        };
+
+        let msg = match msg {
+            ServerLiveMessage::Notification(NotificationMsg::SEND_GIFT { data }) => {
+                match &mut pending {
+                    Some(gift) if gift.uid == data.uid && gift.gift_id == data.gift_id => {
+                        gift.num = gift.num.saturating_add(data.num);
+                        gift.total_coin = gift.total_coin.saturating_add(data.total_coin);
+                        continue;
+                    }
+                    _ => {
+                        deadline = Instant::now() + window;
+                        match pending.replace(data) {
+                            Some(gift) => gift_msg(gift),
+                            None => continue,
+                        }
+                    }
+                }
+            }
+            // 连接状态变化不能因为通道满了被丢掉
+            msg @ (ServerLiveMessage::Connected
+            | ServerLiveMessage::Reconnecting { .. }
+            | ServerLiveMessage::Disconnected) => {
+                outbox.send_control(msg).await?;
+                continue;
+            }
+            msg => msg,
+        };
+        outbox.send(msg).await?;
+    }
+}
@@ -1,17 +1,16 @@
 use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
-use std::collections::LinkedList;
-use std::io::Cursor;
+use serde::{Deserialize, Serialize};
 use std::io::Read;
 use thiserror::Error;
 
 #[allow(non_camel_case_types)]
 pub mod notification_msg {
-    use serde::de::Error;
     use serde::{Deserialize, Serialize};
-    use serde_json::Value;
 
-    #[derive(Deserialize, Serialize, Debug)]
-    #[serde(tag = "cmd")]
+    pub use crate::api::{Medal, OnlineUser};
+
+    #[derive(Deserialize, Serialize, Debug, Clone)]
+    #[serde(tag = "cmd", remote = "Self")]
     pub enum NotificationMsg {
         LIVE {},
         LIVE_ROOM_TOAST_MESSAGE {},
@@ -44,13 +43,15 @@ pub mod notification_msg {
         INTERACT_WORD {
             data: Interact,
         },
-        /// 飘屏
+        /// 飘屏，字段直接在消息的最外层，没有 `data`
         NOTICE_MSG {
-            #[cfg(debug_assertions)]
             #[serde(flatten)]
-            extra: serde_json::Value,
+            data: NoticeMsg,
+        },
+        /// 最近下播的直播间，所有直播间都会收到，不只是当前直播间
+        STOP_LIVE_ROOM_LIST {
+            data: StopLiveRoomList,
         },
-        STOP_LIVE_ROOM_LIST {},
         SEND_GIFT {
             data: OneGift,
         },
@@ -60,6 +61,14 @@ pub mod notification_msg {
         GUARD_BUY {
             data: GuardBuy,
         },
+        /// 醒目留言
+        SUPER_CHAT_MESSAGE {
+            data: SuperChat,
+        },
+        /// 醒目留言被删除或过期
+        SUPER_CHAT_MESSAGE_DELETE {
+            data: SuperChatDelete,
+        },
         CUT_OFF {},
         ROOM_BLOCK_MSG {},
         ROOM_CHANGE {
@@ -74,29 +83,44 @@ pub mod notification_msg {
             extra: serde_json::Value,
         },
         POPULARITY_RED_POCKET_NEW {},
-        POPULARITY_RED_POCKET_START {},
+        /// 红包开始，参与方式见 `APIClient::join_red_pocket`
+        POPULARITY_RED_POCKET_START {
+            data: RedPocket,
+        },
         POPULAR_RANK_CHANGED {},
-        POPULARITY_RED_POCKET_WINNER_LIST {},
+        /// 红包开奖
+        POPULARITY_RED_POCKET_WINNER_LIST {
+            data: RedPocketWinnerList,
+        },
         /// 大家都在说 xxx，第一次看见是在弱酱直播间
         DM_INTERACTION {
-            #[cfg(debug_assertions)]
-            #[serde(flatten)]
-            extra: serde_json::Value,
+            data: DmInteraction,
         },
         HOT_RANK_CHANGED {},
         HOT_RANK_SETTLEMENT {},
         ONLINE_RANK_TOP3 {},
+        /// 高能用户数
         ONLINE_RANK_COUNT {
-            #[cfg(debug_assertions)]
-            #[serde(flatten)]
-            extra: serde_json::Value,
+            data: OnlineRankCount,
         },
         ONLINE_RANK_V2 {
             data: RankData,
         },
         PK_BATTLE_PRE {},
-        PK_BATTLE_START {},
-        PK_BATTLE_END {},
+        /// PK 开始，`pk_id` 在消息的最外层
+        PK_BATTLE_START {
+            #[serde(default)]
+            #[serde(deserialize_with = "u64_or_string")]
+            pk_id: u64,
+            data: PkStart,
+        },
+        /// PK 结束
+        PK_BATTLE_END {
+            #[serde(default)]
+            #[serde(deserialize_with = "u64_or_string")]
+            pk_id: u64,
+            data: PkEnd,
+        },
         PK_BATTLE_MULTIPLE_BEGIN {},
         PK_BATTLE_MULTIPLE_AWARD {},
         /// 视频 pk 结束
@@ -108,7 +132,13 @@ pub mod notification_msg {
         PK_BATTLE_SETTLE {},
         PK_BATTLE_PRE_NEW {},
         PK_BATTLE_START_NEW {},
-        PK_BATTLE_PROCESS_NEW {},
+        /// PK 过程中双方票数变化
+        PK_BATTLE_PROCESS_NEW {
+            #[serde(default)]
+            #[serde(deserialize_with = "u64_or_string")]
+            pk_id: u64,
+            data: PkProcess,
+        },
         PK_BATTLE_FINAL_PROCESS {},
         PK_BATTLE_MULTIPLE_DRAW_RES {},
         /// 看不懂..
@@ -133,9 +163,7 @@ pub mod notification_msg {
         },
         /// PK状态时系统消息
         COMMON_NOTICE_DANMAKU {
-            #[cfg(debug_assertions)]
-            #[serde(flatten)]
-            extra: serde_json::Value,
+            data: CommonNotice,
         },
         /// 点赞积攒时刻
         COLLECTION_PRAISE_UPDATE_PROCESS {
@@ -156,9 +184,7 @@ pub mod notification_msg {
         },
         /// 看过的人
         WATCHED_CHANGE {
-            #[cfg(debug_assertions)]
-            #[serde(flatten)]
-            extra: serde_json::Value,
+            data: WatchedChange,
         },
         /// 分区榜单 rank 改变
         AREA_RANK_CHANGED {
@@ -166,7 +192,10 @@ pub mod notification_msg {
             #[serde(flatten)]
             extra: serde_json::Value,
         },
-        ANCHOR_LOT_START {},
+        /// 天选时刻开始，参与方式见 `APIClient::join_anchor_lot`
+        ANCHOR_LOT_START {
+            data: LotStart,
+        },
         ANCHOR_LOT_END {
             #[cfg(debug_assertions)]
             #[serde(flatten)]
@@ -177,9 +206,18 @@ pub mod notification_msg {
             #[serde(flatten)]
             extra: serde_json::Value,
         },
-        ANCHOR_LOT_AWARD {},
-        LIKE_INFO_V3_UPDATE {},
-        LIKE_INFO_V3_CLICK {},
+        /// 天选时刻开奖
+        ANCHOR_LOT_AWARD {
+            data: LotAward,
+        },
+        /// 点赞总数变化
+        LIKE_INFO_V3_UPDATE {
+            data: LikeUpdate,
+        },
+        /// 有人点赞
+        LIKE_INFO_V3_CLICK {
+            data: LikeClick,
+        },
         GIFT_STAR_PROCESS {},
         GIFT_PANEL_PLAN {},
         WIDGET_WISH_LIST {},
@@ -188,77 +226,311 @@ pub mod notification_msg {
         PREPARING {
             roomid: String,
         },
+        /// 还没有收录的 cmd
+        ///
+        /// `raw` 为除 `cmd` 以外的全部字段，序列化时会还原成原始消息。
+        /// 已收录的 cmd 格式对不上时反序列化会返回错误，不会落到这里
+        #[serde(skip)]
+        Unknown {
+            cmd: String,
+            raw: serde_json::Value,
+        },
+    }
+
+    impl Serialize for NotificationMsg {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::SerializeMap;
+
+            let NotificationMsg::Unknown { cmd, raw } = self else {
+                return NotificationMsg::serialize(self, serializer);
+            };
+            let mut map = serializer.serialize_map(None)?;
+            map.serialize_entry("cmd", cmd)?;
+            if let Some(fields) = raw.as_object() {
+                for (k, v) in fields {
+                    map.serialize_entry(k, v)?;
+                }
+            }
+            map.end()
+        }
+    }
+
+    impl<'de> Deserialize<'de> for NotificationMsg {
+        /// 已收录的 cmd 字段对不上时返回错误，没有收录的 cmd 为 `Unknown`
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            use serde::de::Error;
+
+            let value = serde_json::Value::deserialize(deserializer)?;
+            match NotificationMsg::deserialize(&value) {
+                Ok(msg) => Ok(msg),
+                Err(e) => NotificationMsg::unknown_or(value, e).map_err(D::Error::custom),
+            }
+        }
+    }
+
+    impl NotificationMsg {
+        /// 同 `serde_json::from_slice`，已收录的 cmd 只解析一次
+        pub fn from_slice(body: &[u8]) -> Result<Self, serde_json::Error> {
+            let mut de = serde_json::Deserializer::from_slice(body);
+            match NotificationMsg::deserialize(&mut de).and_then(|msg| de.end().map(|_| msg)) {
+                Ok(msg) => Ok(msg),
+                Err(e) => NotificationMsg::unknown_or(serde_json::from_slice(body)?, e),
+            }
+        }
+
+        /// 解析失败时，cmd 没有收录则返回 `Unknown`，否则返回原来的错误
+        fn unknown_or(
+            mut value: serde_json::Value,
+            e: serde_json::Error,
+        ) -> Result<Self, serde_json::Error> {
+            use serde::de::Error;
+
+            let cmd = match value.get("cmd") {
+                Some(serde_json::Value::String(cmd)) => cmd.clone(),
+                _ => return Err(e),
+            };
+            if known_cmds().contains(&cmd.as_str()) {
+                return Err(serde_json::Error::custom(format!("{cmd}: {e}")));
+            }
+            if let Some(map) = value.as_object_mut() {
+                map.remove("cmd");
+            }
+            Ok(NotificationMsg::Unknown { cmd, raw: value })
+        }
     }
 
-    #[derive(Serialize, Debug)]
+    /// `NotificationMsg` 收录的全部 cmd
+    ///
+    /// 用一个不存在的 cmd 去反序列化，从 `unknown_variant` 的参数里拿到 serde 生成的变体列表
+    fn known_cmds() -> &'static [&'static str] {
+        static KNOWN: std::sync::OnceLock<&'static [&'static str]> = std::sync::OnceLock::new();
+        KNOWN.get_or_init(|| {
+            let de = serde::de::value::MapDeserializer::<_, VariantsProbe>::new(std::iter::once((
+                "cmd", "",
+            )));
+            match NotificationMsg::deserialize(de) {
+                Err(VariantsProbe(Some(variants))) => variants,
+                _ => &[],
+            }
+        })
+    }
+
+    #[derive(Debug)]
+    struct VariantsProbe(Option<&'static [&'static str]>);
+
+    impl std::fmt::Display for VariantsProbe {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+
+    impl std::error::Error for VariantsProbe {}
+
+    impl serde::de::Error for VariantsProbe {
+        fn custom<T: std::fmt::Display>(_msg: T) -> Self {
+            VariantsProbe(None)
+        }
+
+        fn unknown_variant(_variant: &str, expected: &'static [&'static str]) -> Self {
+            VariantsProbe(Some(expected))
+        }
+    }
+
+    #[derive(Serialize, Default, Debug, Clone)]
     pub struct DanmuMsg {
         pub uid: u64,
         pub uname: String,
 
-        /// 1总督 2提督 3舰长
-        pub guard_level: u32,
+        /// 在这个直播间的大航海等级，没有大航海时为 `None`
+        pub guard_level: Option<GuardLevel>,
+        /// `info[7]` 缺失时粉丝勋章里的大航海等级，只对勋章所属的主播有效，见 `guard_level_in`
+        pub medal_guard_level: Option<GuardLevel>,
 
         pub medal_lv: u32,
         pub medal_name: String,
         pub medal_owner_uid: u64,
         pub medal_owner_name: String,
+        /// 勋章的渐变色和边框颜色，十进制 RGB
+        pub medal_color_start: u32,
+        pub medal_color_end: u32,
+        pub medal_color_border: u32,
+        /// 勋章是否点亮，未点亮的勋章显示为灰色
+        pub is_lighted: bool,
 
+        /// 表情弹幕时为表情的名字
         pub text: String,
+        /// 表情弹幕的图片地址
+        pub emoticon: Option<String>,
+
+        /// 发送时间，unix 毫秒时间戳
+        pub timestamp: u64,
     }
 
     impl<'de> Deserialize<'de> for DanmuMsg {
+        /// 格式不对的字段会被置为默认值，不会导致整个包解析失败
         fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
         where
             D: serde::Deserializer<'de>,
         {
             let info = serde_json::Value::deserialize(deserializer)?;
+            // 由 `DanmuMsg` 自己序列化出来的对象
+            if info.is_object() {
+                return Ok(Self::from_object(&info));
+            }
+            if !info.is_array() {
+                log::warn!("DANMU_MSG info type error: {}", info);
+                return Ok(DanmuMsg::default());
+            }
 
-            match info {
-                Value::Array(ref info) => match info.as_slice() {
-                    [_, Value::String(text), Value::Array(user), Value::Array(up), _, _, _, Value::Number(guard_level), ..] =>
-                    {
-                        let uid = user.get(0).and_then(|v| v.as_u64()).unwrap_or(0);
-                        let uname = user
-                            .get(1)
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .to_string();
-
-                        let guard_level = guard_level.as_u64().unwrap_or_default() as u32;
-
-                        let card_lv = up.get(0).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
-                        let card_name =
-                            up.get(1).and_then(|v| v.as_str()).unwrap_or("").to_string();
-                        let up_uid = up.last().and_then(|v| v.as_u64()).unwrap_or(0);
-                        let up_name = up.get(2).and_then(|v| v.as_str()).unwrap_or("").to_string();
-
-                        Ok(DanmuMsg {
-                            uid,
-                            uname,
-                            guard_level,
-                            medal_lv: card_lv,
-                            medal_name: card_name,
-                            medal_owner_uid: up_uid,
-                            medal_owner_name: up_name,
-                            text: text.to_string(),
-                        })
-                    }
-                    _ => Err(Error::custom("info format error")),
-                },
-                _ => Err(Error::custom("info type error")),
+            let meta = &info[0];
+            let user = &info[2];
+            let up = &info[3];
+
+            let uid = user[0].as_u64().unwrap_or(0);
+            let uname = user[1].as_str().unwrap_or("").to_string();
+
+            // info[7] 通常是大航海等级，0 表示在这个直播间没有大航海；
+            // 不是数字时只能看粉丝勋章 info[3][10]，但勋章可能是别的主播的
+            let level_of = |v: &serde_json::Value| {
+                v.as_u64().and_then(|l| GuardLevel::try_from(l as u32).ok())
+            };
+            let (guard_level, medal_guard_level) = if info[7].is_number() {
+                (level_of(&info[7]), None)
+            } else {
+                (None, level_of(&up[10]))
+            };
+
+            let card_lv = up[0].as_u64().unwrap_or(0) as u32;
+            let card_name = up[1].as_str().unwrap_or("").to_string();
+            let up_uid = up
+                .as_array()
+                .and_then(|up| up.last())
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0);
+            let up_name = up[2].as_str().unwrap_or("").to_string();
+            let color_of = |i: usize| up[i].as_u64().unwrap_or(0) as u32;
+
+            let timestamp = meta[4].as_u64().unwrap_or(0);
+
+            // 表情弹幕的 info[0][13] 是一个对象，普通弹幕是 "{}" 字符串
+            let emoticon = meta[13]["url"].as_str().map(|s| s.to_string());
+            let mut text = info[1].as_str().unwrap_or("").to_string();
+            if text.is_empty() {
+                if let Some(name) = meta[13]["emoticon_unique"].as_str() {
+                    text = name.to_string();
+                } else if let Some(url) = &emoticon {
+                    text = url.clone();
+                }
             }
+
+            Ok(DanmuMsg {
+                uid,
+                uname,
+                guard_level,
+                medal_guard_level,
+                medal_lv: card_lv,
+                medal_name: card_name,
+                medal_owner_uid: up_uid,
+                medal_owner_name: up_name,
+                medal_color_start: color_of(8),
+                medal_color_end: color_of(9),
+                medal_color_border: color_of(7),
+                is_lighted: up[11].as_u64().unwrap_or(0) == 1,
+                text,
+                emoticon,
+                timestamp,
+            })
         }
     }
 
-    #[derive(Deserialize, Serialize, Default, Debug)]
-    pub struct OnlineUser {
-        pub guard_level: u32,
-        pub rank: usize,
-        pub uid: u64,
-        pub uname: String,
+    impl DanmuMsg {
+        /// 在主播 `anchor_uid` 的直播间里的大航海等级
+        ///
+        /// `info[7]` 缺失时使用粉丝勋章里的等级，前提是勋章属于这个主播
+        pub fn guard_level_in(&self, anchor_uid: u64) -> Option<GuardLevel> {
+            if self.guard_level.is_some() || self.medal_owner_uid != anchor_uid {
+                return self.guard_level;
+            }
+            self.medal_guard_level
+        }
+
+        fn from_object(v: &serde_json::Value) -> Self {
+            let u64_of = |key: &str| v[key].as_u64().unwrap_or_default();
+            let string_of = |key: &str| v[key].as_str().unwrap_or_default().to_string();
+            DanmuMsg {
+                uid: u64_of("uid"),
+                uname: string_of("uname"),
+                guard_level: GuardLevel::try_from(u64_of("guard_level") as u32).ok(),
+                medal_guard_level: GuardLevel::try_from(u64_of("medal_guard_level") as u32).ok(),
+                medal_lv: u64_of("medal_lv") as u32,
+                medal_name: string_of("medal_name"),
+                medal_owner_uid: u64_of("medal_owner_uid"),
+                medal_owner_name: string_of("medal_owner_name"),
+                medal_color_start: u64_of("medal_color_start") as u32,
+                medal_color_end: u64_of("medal_color_end") as u32,
+                medal_color_border: u64_of("medal_color_border") as u32,
+                is_lighted: v["is_lighted"].as_bool().unwrap_or_default(),
+                text: string_of("text"),
+                emoticon: v["emoticon"].as_str().map(|s| s.to_string()),
+                timestamp: u64_of("timestamp"),
+            }
+        }
     }
 
-    #[derive(Deserialize, Serialize, Default, Debug)]
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct NoticeMsg {
+        /// 比如 "<%某人%> 在 <%某主播%> 的房间开通了舰长"
+        #[serde(default)]
+        pub msg_common: String,
+        /// 在当前直播间显示的文字
+        #[serde(default)]
+        pub msg_self: String,
+        /// 事件发生的直播间
+        #[serde(default)]
+        pub roomid: u64,
+        #[serde(default)]
+        pub real_roomid: u64,
+        #[serde(default)]
+        pub msg_type: u32,
+        #[serde(default)]
+        pub link_url: String,
+        #[serde(default)]
+        pub full: NoticeStyle,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct NoticeStyle {
+        #[serde(default)]
+        #[serde(alias = "background")]
+        pub background_color: String,
+        #[serde(default)]
+        pub color: String,
+        #[serde(default)]
+        pub highlight: String,
+        /// 显示时长，秒
+        #[serde(default)]
+        pub time: u32,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct WatchedChange {
+        #[serde(default)]
+        pub num: u64,
+        #[serde(default)]
+        pub text_small: String,
+        #[serde(default)]
+        pub text_large: String,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct OnlineRankCount {
+        #[serde(default)]
+        pub count: u64,
+        #[serde(default)]
+        pub online_count: Option<u64>,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
     pub struct RankData {
         #[serde(default)]
         #[serde(alias = "list")]
@@ -266,15 +538,32 @@ pub mod notification_msg {
         pub rank_type: String,
     }
 
-    #[derive(Deserialize, Serialize, Default, Debug)]
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
     pub struct EntryEffect {
         #[serde(default)]
         pub uid: u64,
         #[serde(default)]
         pub copy_writing: String,
+        /// 带格式的欢迎文字，如 `<%用户名%> 进入直播间`
+        #[serde(default)]
+        pub copy_writing_v2: String,
+        #[serde(default)]
+        pub highlight_color: String,
+        /// 大航海等级，0 表示不是舰长，见 `guard_level`
+        #[serde(default)]
+        pub privilege_type: u32,
+        /// 头像地址
+        #[serde(default)]
+        pub face: String,
     }
 
-    #[derive(Deserialize, Serialize, Debug)]
+    impl EntryEffect {
+        pub fn guard_level(&self) -> Option<GuardLevel> {
+            GuardLevel::try_from(self.privilege_type).ok()
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone)]
     pub struct Interact {
         #[serde(default)]
         pub uid: u64,
@@ -282,35 +571,468 @@ pub mod notification_msg {
         pub uname: String,
         #[serde(default)]
         pub fans_medal: Option<Medal>,
-        /**
-        1. 进入直播间
-        2. 关注直播间
-        3. 分享直播间
-        4. 未知
-        5. 互关
-        */
-        pub msg_type: u32,
+        pub msg_type: InteractType,
     }
 
-    #[derive(Deserialize, Serialize, Default, Debug)]
-    pub struct Medal {
-        pub anchor_roomid: u32,
-        pub guard_level: u32,
-        pub medal_level: u32,
-        pub medal_name: String,
+    /// `INTERACT_WORD` 的 `msg_type`，序列化为数字
+    #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[serde(from = "u32", into = "u32")]
+    pub enum InteractType {
+        /// 1 进入直播间
+        Enter,
+        /// 2 关注直播间
+        Follow,
+        /// 3 分享直播间
+        Share,
+        /// 4 未知
+        Unknown,
+        /// 5 互关
+        MutualFollow,
+        /// 还没有收录的类型，保留原始值
+        Other(u32),
     }
 
-    #[derive(Deserialize, Serialize, Debug)]
+    impl InteractType {
+        pub fn value(&self) -> u32 {
+            match self {
+                InteractType::Enter => 1,
+                InteractType::Follow => 2,
+                InteractType::Share => 3,
+                InteractType::Unknown => 4,
+                InteractType::MutualFollow => 5,
+                InteractType::Other(v) => *v,
+            }
+        }
+    }
+
+    impl From<u32> for InteractType {
+        fn from(v: u32) -> Self {
+            match v {
+                1 => InteractType::Enter,
+                2 => InteractType::Follow,
+                3 => InteractType::Share,
+                4 => InteractType::Unknown,
+                5 => InteractType::MutualFollow,
+                v => InteractType::Other(v),
+            }
+        }
+    }
+
+    impl From<InteractType> for u32 {
+        fn from(t: InteractType) -> Self {
+            t.value()
+        }
+    }
+
+    impl std::fmt::Display for InteractType {
+        /// 可以直接拼在用户名后面，比如 "xxx 进入直播间"
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                InteractType::Enter => f.write_str("进入直播间"),
+                InteractType::Follow => f.write_str("关注了直播间"),
+                InteractType::Share => f.write_str("分享了直播间"),
+                InteractType::Unknown => f.write_str("与直播间互动"),
+                InteractType::MutualFollow => f.write_str("与主播互关了"),
+                InteractType::Other(v) => write!(f, "与直播间互动({})", v),
+            }
+        }
+    }
+
+    /// 大航海等级，序列化为数字
+    #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[serde(try_from = "u32", into = "u32")]
+    pub enum GuardLevel {
+        /// 总督
+        Governor = 1,
+        /// 提督
+        Admiral = 2,
+        /// 舰长
+        Captain = 3,
+    }
+
+    impl TryFrom<u32> for GuardLevel {
+        type Error = String;
+
+        fn try_from(level: u32) -> Result<Self, Self::Error> {
+            match level {
+                1 => Ok(GuardLevel::Governor),
+                2 => Ok(GuardLevel::Admiral),
+                3 => Ok(GuardLevel::Captain),
+                _ => Err(format!("unknown guard level {}", level)),
+            }
+        }
+    }
+
+    impl From<GuardLevel> for u32 {
+        fn from(level: GuardLevel) -> Self {
+            level as u32
+        }
+    }
+
+    impl std::fmt::Display for GuardLevel {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let name = match self {
+                GuardLevel::Governor => "总督",
+                GuardLevel::Admiral => "提督",
+                GuardLevel::Captain => "舰长",
+            };
+            f.write_str(name)
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone)]
     pub struct GuardBuy {
         pub gift_id: u32,
         pub gift_name: String,
-        pub guard_level: u32,
+        pub guard_level: GuardLevel,
         pub num: u32,
         pub uid: u64,
         pub username: String,
+        /// 单价，单位：金瓜子（1000 金瓜子 = 1 元）
+        #[serde(default)]
+        pub price: u64,
+        #[serde(default)]
+        pub start_time: u64,
+        #[serde(default)]
+        pub end_time: u64,
+    }
+
+    /// 互动聚合提示，`interaction_type` 为 102 时是"大家都在说"，
+    /// 103 关注、104 送礼、105 分享、106 点赞时只有 `fresh_title` 之类的字段，放在 `detail` 里
+    #[derive(Serialize, Default, Debug, Clone)]
+    pub struct DmInteraction {
+        pub id: u64,
+        pub interaction_type: u32,
+        pub status: u32,
+        pub combo: Vec<DmCombo>,
+        /// 解析后的 `data` 字段
+        pub detail: serde_json::Value,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct DmCombo {
+        /// 刷屏的内容
+        #[serde(default)]
+        pub content: String,
+        /// 发送人数
+        #[serde(default)]
+        pub cnt: u32,
+        /// 比如 "他们都在说:"
+        #[serde(default)]
+        pub guide: String,
+    }
+
+    impl<'de> Deserialize<'de> for DmInteraction {
+        /// `data` 是一个 json 字符串，自己序列化出来的是对象
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let v = serde_json::Value::deserialize(deserializer)?;
+            let u64_of = |key: &str| v[key].as_u64().unwrap_or_default();
+            let detail = match &v["data"] {
+                serde_json::Value::String(s) => serde_json::from_str(s).unwrap_or_default(),
+                serde_json::Value::Null => v["detail"].clone(),
+                other => other.clone(),
+            };
+            let combo = match detail.get("combo") {
+                Some(combo) => serde_json::from_value(combo.clone()).unwrap_or_default(),
+                None => serde_json::from_value(v["combo"].clone()).unwrap_or_default(),
+            };
+            Ok(DmInteraction {
+                id: u64_of("id"),
+                interaction_type: v["type"]
+                    .as_u64()
+                    .or(v["interaction_type"].as_u64())
+                    .unwrap_or_default() as u32,
+                status: u64_of("status") as u32,
+                combo,
+                detail,
+            })
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct StopLiveRoomList {
+        #[serde(default)]
+        pub room_id_list: Vec<u64>,
+    }
+
+    impl StopLiveRoomList {
+        pub fn contains(&self, room_id: u64) -> bool {
+            self.room_id_list.contains(&room_id)
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct LotStart {
+        #[serde(default)]
+        pub id: u64,
+        #[serde(default)]
+        pub award_name: String,
+        #[serde(default)]
+        pub award_num: u32,
+        /// 参与条件，0 无 1 关注主播 2 粉丝勋章 3 大航海
+        #[serde(default)]
+        pub require_type: u32,
+        /// 需要的勋章等级或大航海等级
+        #[serde(default)]
+        pub require_value: u32,
+        #[serde(default)]
+        pub require_text: String,
+        /// 参与时发送的弹幕
+        #[serde(default)]
+        pub danmu: String,
+        /// 需要送的礼物，0 表示不需要送礼
+        #[serde(default)]
+        pub gift_id: u64,
+        #[serde(default)]
+        pub gift_name: String,
+        #[serde(default)]
+        pub gift_num: u32,
+        /// 总时长，秒
+        #[serde(default)]
+        pub max_time: u64,
+        /// 剩余时间，秒
+        #[serde(default)]
+        pub time: u64,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct LotAward {
+        #[serde(default)]
+        pub id: u64,
+        #[serde(default)]
+        pub award_name: String,
+        #[serde(default)]
+        pub award_num: u32,
+        #[serde(default)]
+        pub award_users: Vec<LotWinner>,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct LotWinner {
+        #[serde(default)]
+        pub uid: u64,
+        #[serde(default)]
+        pub uname: String,
+        #[serde(default)]
+        pub face: String,
+        #[serde(default)]
+        pub num: u32,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct LikeUpdate {
+        /// 本场直播的点赞总数
+        #[serde(default)]
+        pub click_count: u64,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct LikeClick {
+        #[serde(default)]
+        pub uid: u64,
+        #[serde(default)]
+        pub uname: String,
+        /// 如 "为主播点赞了"
+        #[serde(default)]
+        pub like_text: String,
+        #[serde(default)]
+        pub fans_medal: Option<Medal>,
+    }
+
+    /// `pk_id` 有时是数字有时是字符串
+    fn u64_or_string<'de, D>(deserializer: D) -> Result<u64, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let v = serde_json::Value::deserialize(deserializer)?;
+        Ok(v.as_u64()
+            .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+            .unwrap_or_default())
+    }
+
+    /// PK 中一方直播间的信息，`init_info` 为发起方，`match_info` 为对手
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct PkRoom {
+        #[serde(default)]
+        pub room_id: u64,
+        /// 当前票数
+        #[serde(default)]
+        pub votes: u64,
+        /// 贡献最多的用户
+        #[serde(default)]
+        pub best_uname: String,
+        /// 只在 `PK_BATTLE_END` 里有，2 胜 1 平 -1 负
+        #[serde(default)]
+        pub winner_type: i32,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct PkStart {
+        #[serde(default)]
+        pub battle_type: u32,
+        /// unix 秒
+        #[serde(default)]
+        #[serde(alias = "pk_start_time")]
+        pub start_time: u64,
+        #[serde(default)]
+        #[serde(alias = "pk_end_time")]
+        pub end_time: u64,
+        #[serde(default)]
+        pub init_info: PkRoom,
+        #[serde(default)]
+        pub match_info: PkRoom,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct PkProcess {
+        #[serde(default)]
+        pub battle_type: u32,
+        #[serde(default)]
+        pub init_info: PkRoom,
+        #[serde(default)]
+        pub match_info: PkRoom,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct PkEnd {
+        #[serde(default)]
+        pub battle_type: u32,
+        #[serde(default)]
+        pub init_info: PkRoom,
+        #[serde(default)]
+        pub match_info: PkRoom,
+    }
+
+    impl PkEnd {
+        /// 获胜的一方，平局时为 `None`
+        pub fn winner(&self) -> Option<&PkRoom> {
+            [&self.init_info, &self.match_info]
+                .into_iter()
+                .find(|r| r.winner_type == 2)
+        }
+    }
+
+    /// PK 等系统提示，由多段文字组成
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct CommonNotice {
+        #[serde(default)]
+        pub content_segments: Vec<NoticeSegment>,
     }
 
-    #[derive(Deserialize, Serialize, Debug)]
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct NoticeSegment {
+        /// 高亮的部分用 `<$` 和 `$>` 包起来
+        #[serde(default)]
+        pub text: String,
+        #[serde(default)]
+        pub font_color: String,
+        #[serde(default)]
+        #[serde(rename = "type")]
+        pub segment_type: u32,
+    }
+
+    impl CommonNotice {
+        /// 拼接所有文字，去掉高亮标记
+        pub fn text(&self) -> String {
+            self.content_segments
+                .iter()
+                .map(|s| s.text.replace("<$", "").replace("$>", ""))
+                .collect()
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct RedPocket {
+        #[serde(default)]
+        pub lot_id: u64,
+        #[serde(default)]
+        pub sender_uid: u64,
+        #[serde(default)]
+        pub sender_name: String,
+        /// 参与条件，1 为关注主播
+        #[serde(default)]
+        pub join_requirement: u32,
+        /// 参与时自动发送的弹幕
+        #[serde(default)]
+        pub danmu: String,
+        /// unix 秒
+        #[serde(default)]
+        pub start_time: u64,
+        #[serde(default)]
+        pub end_time: u64,
+        /// 持续时间，秒
+        #[serde(default)]
+        pub last_time: u64,
+        /// 总价值，单位：金瓜子
+        #[serde(default)]
+        pub total_price: u64,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct RedPocketWinnerList {
+        #[serde(default)]
+        pub lot_id: u64,
+        #[serde(default)]
+        pub total_num: u32,
+        #[serde(default)]
+        #[serde(rename = "winner_info")]
+        pub winners: Vec<RedPocketWinner>,
+    }
+
+    /// 中奖用户，原始数据是 `[uid, 用户名, ...]` 这样的数组
+    #[derive(Serialize, Default, Debug, Clone)]
+    pub struct RedPocketWinner {
+        pub uid: u64,
+        pub uname: String,
+    }
+
+    impl<'de> Deserialize<'de> for RedPocketWinner {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let v = serde_json::Value::deserialize(deserializer)?;
+            let (uid, uname) = if v.is_array() {
+                (&v[0], &v[1])
+            } else {
+                (&v["uid"], &v["uname"])
+            };
+            Ok(RedPocketWinner {
+                uid: uid.as_u64().unwrap_or_default(),
+                uname: uname.as_str().unwrap_or_default().to_string(),
+            })
+        }
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct SuperChatUser {
+        #[serde(default)]
+        pub uname: String,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone)]
+    pub struct SuperChat {
+        pub id: u64,
+        pub uid: u64,
+        #[serde(default)]
+        pub user_info: SuperChatUser,
+        pub message: String,
+        /// 单位：元
+        pub price: u32,
+        pub start_time: u64,
+        pub end_time: u64,
+    }
+
+    #[derive(Deserialize, Serialize, Default, Debug, Clone)]
+    pub struct SuperChatDelete {
+        #[serde(default)]
+        pub ids: Vec<u64>,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, Clone)]
     pub struct OneGift {
         #[serde(rename = "giftId")]
         pub gift_id: u32,
@@ -322,7 +1044,7 @@ pub mod notification_msg {
         pub uname: String,
     }
 
-    #[derive(Deserialize, Serialize, Debug)]
+    #[derive(Deserialize, Serialize, Debug, Clone)]
     pub struct BatchGift {
         pub gift_id: u32,
         pub gift_name: String,
@@ -332,27 +1054,61 @@ pub mod notification_msg {
         pub uname: String,
     }
 }
-#[derive(Debug)]
+/// 序列化为 `{"type": "ServerHeartBeat", "data": 1234}` 的形式
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", content = "data")]
 pub enum ServerLiveMessage {
     LoginAck,
     Notification(notification_msg::NotificationMsg),
-    ServerHeartBeat,
+    /// 心跳回复，携带当前直播间人气值
+    ServerHeartBeat(u32),
+    /// 登录成功，连接已建立
+    Connected,
+    /// 连接断开，准备第 `attempt` 次重连
+    Reconnecting {
+        attempt: u32,
+    },
+    /// websocket 连接已断开
+    Disconnected,
 }
 
-#[derive(Debug, Clone)]
+impl ServerLiveMessage {
+    /// 当前直播间下播（`PREPARING`）或者被切断（`CUT_OFF`）
+    ///
+    /// `STOP_LIVE_ROOM_LIST` 是全站的下播列表，需要用 `StopLiveRoomList::contains` 判断
+    pub fn is_stream_end(&self) -> bool {
+        matches!(
+            self,
+            ServerLiveMessage::Notification(
+                notification_msg::NotificationMsg::PREPARING { .. }
+                    | notification_msg::NotificationMsg::CUT_OFF {}
+            )
+        )
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct WsLogin {
     pub room_id: u64,
     pub uid: u64,
     pub key: String,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", content = "data")]
 pub enum ClientLiveMessage {
     Login(WsLogin),
     ClientHeartBeat,
 }
 
 impl ClientLiveMessage {
+    /// 同 `encode_with_seq`，序号固定为 1
     pub fn encode(&self) -> Vec<u8> {
+        self.encode_with_seq(1)
+    }
+
+    /// 编码为数据包，`seq` 为包头最后的序号，同一个连接里应该递增
+    pub fn encode_with_seq(&self, seq: u32) -> Vec<u8> {
         match self {
             ClientLiveMessage::Login(WsLogin { room_id, uid, key }) => {
                 let uid = if *uid > 0 { Some(*uid) } else { None };
@@ -374,7 +1130,7 @@ impl ClientLiveMessage {
                 package.write_u16::<NetworkEndian>(16).unwrap();
                 package.write_u16::<NetworkEndian>(1).unwrap();
                 package.write_u32::<NetworkEndian>(7).unwrap();
-                package.write_u32::<NetworkEndian>(1).unwrap();
+                package.write_u32::<NetworkEndian>(seq).unwrap();
                 package.extend_from_slice(payload.as_bytes());
                 package
             }
@@ -388,7 +1144,7 @@ impl ClientLiveMessage {
                 package.write_u16::<NetworkEndian>(16).unwrap();
                 package.write_u16::<NetworkEndian>(1).unwrap();
                 package.write_u32::<NetworkEndian>(2).unwrap();
-                package.write_u32::<NetworkEndian>(1).unwrap();
+                package.write_u32::<NetworkEndian>(seq).unwrap();
                 package.extend_from_slice(payload);
                 package
             }
@@ -404,84 +1160,208 @@ pub enum MsgDecodeError {
     UselessMsg(usize),
     #[error("inflate error {0}")]
     InflateError(String),
+    #[error("brotli error {0}")]
+    BrotliError(String),
     #[error("undefine msg v={pkg_v:?} type={pkg_type:?}")]
     UndefinedMsg { pkg_v: u16, pkg_type: u32 },
     #[error("decode body is error {0}")]
     DecodeBodyError(String),
 }
 
+/// 解码服务器发来的一帧数据，一帧中可能包含多条消息
+///
+/// 单条消息解析失败不会中断整帧的解码，失败的消息会被跳过，错误放入 `errors`。
+/// 只有包头或解压出错导致无法继续时才返回 `Err`。
 pub fn decode_from_server(
     data: Vec<u8>,
-    result_list: &mut LinkedList<ServerLiveMessage>,
+    result_list: &mut Vec<ServerLiveMessage>,
+    errors: &mut Vec<MsgDecodeError>,
 ) -> Result<(), MsgDecodeError> {
-    let mut buff_len = data.len();
-    let mut buff = Cursor::new(data);
-    'start: loop {
-        let package_length = buff
-            .read_u32::<NetworkEndian>()
-            .map_err(|_| MsgDecodeError::BadHeader)? as usize;
-        let package_head_length = buff
-            .read_u16::<NetworkEndian>()
-            .map_err(|_| MsgDecodeError::BadHeader)? as usize;
-        let package_version = buff
-            .read_u16::<NetworkEndian>()
-            .map_err(|_| MsgDecodeError::BadHeader)?;
-        let package_type = buff
-            .read_u32::<NetworkEndian>()
-            .map_err(|_| MsgDecodeError::BadHeader)?;
-        let package_other = buff
-            .read_u32::<NetworkEndian>()
-            .map_err(|_| MsgDecodeError::BadHeader)?;
+    let mut iter = decode_iter(data);
+    while let Some(r) = iter.next() {
+        match r {
+            Ok(msg) => result_list.push(msg),
+            Err(e) if iter.done => return Err(e),
+            Err(e) => errors.push(e),
+        }
+    }
+    Ok(())
+}
 
-        log::trace!(
-            "package_version={} package_other={}",
-            package_version,
-            package_other
-        );
+/// 协议版本 2 为 zlib，3 为 brotli
+fn decompress(version: u16, body: &[u8]) -> Result<Vec<u8>, MsgDecodeError> {
+    if version == 2 {
+        inflate::inflate_bytes_zlib(body).map_err(MsgDecodeError::InflateError)
+    } else {
+        let mut new_data = vec![];
+        brotli_decompressor::Decompressor::new(body, 4096)
+            .read_to_end(&mut new_data)
+            .map(|_| new_data)
+            .map_err(|e| MsgDecodeError::BrotliError(e.to_string()))
+    }
+}
 
-        if package_version == 2 {
-            let mut package_body = vec![];
-            let _ = buff.read_to_end(&mut package_body);
+/// 一个数据包，包头加包体
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// 协议版本，0 为 json，1 为心跳/认证
+    pub version: u16,
+    /// 操作码，3 心跳回复，5 通知，8 认证回复
+    pub op: u32,
+    pub seq: u32,
+    pub body: FrameBody,
+}
 
-            let new_data = inflate::inflate_bytes_zlib(package_body.as_slice())
-                .map_err(|e| MsgDecodeError::InflateError(e))?;
+#[derive(Debug, Clone)]
+pub enum FrameBody {
+    /// 不认识的操作码或者解析失败的包体
+    Raw(Vec<u8>),
+    Message(ServerLiveMessage),
+}
+
+/// 解码一帧数据，保留每个包的包头，用于调试协议
+///
+/// 压缩的包（版本 2 和 3）会被解压，结果中是解压后的各个包。
+/// 包体解析失败时保留为 `FrameBody::Raw`，只有包头或解压出错时返回 `Err`。
+pub fn decode_frames(data: &[u8]) -> Result<Vec<Frame>, MsgDecodeError> {
+    let mut frames = vec![];
+    decode_frames_into(data, &mut frames)?;
+    Ok(frames)
+}
+
+fn decode_frames_into(data: &[u8], frames: &mut Vec<Frame>) -> Result<(), MsgDecodeError> {
+    let mut packets = vec![];
+    let r = split_packets(data, &mut packets);
+    frames.extend(packets.into_iter().map(|packet| {
+        let body = match parse_packet(&packet) {
+            Ok(msg) => FrameBody::Message(msg),
+            Err(_) => FrameBody::Raw(packet.body),
+        };
+        Frame {
+            version: packet.version,
+            op: packet.op,
+            seq: packet.seq,
+            body,
+        }
+    }));
+    r
+}
+
+/// 拆分后的一个包，包体已经解压
+#[derive(Debug)]
+struct Packet {
+    version: u16,
+    op: u32,
+    seq: u32,
+    body: Vec<u8>,
+}
+
+/// 按包头里的长度拆出一帧里的各个包，压缩的包解压后继续拆分
+///
+/// 出错时 `packets` 里保留出错之前拆出的包
+fn split_packets(mut data: &[u8], packets: &mut Vec<Packet>) -> Result<(), MsgDecodeError> {
+    while !data.is_empty() {
+        let mut header = data;
+        let (package_length, package_head_length, version, op, seq) = (|| {
+            Some((
+                header.read_u32::<NetworkEndian>().ok()? as usize,
+                header.read_u16::<NetworkEndian>().ok()? as usize,
+                header.read_u16::<NetworkEndian>().ok()?,
+                header.read_u32::<NetworkEndian>().ok()?,
+                header.read_u32::<NetworkEndian>().ok()?,
+            ))
+        })()
+        .ok_or(MsgDecodeError::BadHeader)?;
+        // 包长度超出实际数据时说明数据被截断或者包头有误
+        if package_head_length < 16
+            || package_length < package_head_length
+            || package_length > data.len()
+        {
+            return Err(MsgDecodeError::BadHeader);
+        }
+        let body = &data[package_head_length..package_length];
+        data = &data[package_length..];
 
-            buff_len = new_data.len();
-            buff = Cursor::new(new_data);
-            // tail call
-            continue 'start;
+        log::trace!("package_version={} seq={}", version, seq);
+        if version == 2 || version == 3 {
+            split_packets(&decompress(version, body)?, packets)?;
+            continue;
         }
-        if package_version > 2 {
+        if version > 3 {
             return Err(MsgDecodeError::UndefinedMsg {
-                pkg_v: package_version,
-                pkg_type: package_type,
+                pkg_v: version,
+                pkg_type: op,
             });
         }
+        packets.push(Packet {
+            version,
+            op,
+            seq,
+            body: body.to_vec(),
+        });
+    }
+    Ok(())
+}
 
-        let package_body_len = package_length - package_head_length;
-        let mut package_body = vec![0; package_body_len];
-        let _ = buff.read(package_body.as_mut_slice());
-
-        match package_type {
-            3 => result_list.push_back(ServerLiveMessage::ServerHeartBeat),
-            5 => {
-                let notification_msg = serde_json::from_slice(package_body.as_slice())
-                    .map_err(|e| MsgDecodeError::DecodeBodyError(e.to_string()))?;
-                result_list.push_back(ServerLiveMessage::Notification(notification_msg))
-            }
-            8 => result_list.push_back(ServerLiveMessage::LoginAck),
-            _ => {
-                return Err(MsgDecodeError::UndefinedMsg {
-                    pkg_v: package_version,
-                    pkg_type: package_type,
-                });
+fn parse_packet(packet: &Packet) -> Result<ServerLiveMessage, MsgDecodeError> {
+    match packet.op {
+        3 => packet
+            .body
+            .as_slice()
+            .read_u32::<NetworkEndian>()
+            .map(ServerLiveMessage::ServerHeartBeat)
+            .map_err(|e| MsgDecodeError::DecodeBodyError(e.to_string())),
+        5 => match notification_msg::NotificationMsg::from_slice(&packet.body) {
+            Ok(notification_msg) => {
+                if let notification_msg::NotificationMsg::Unknown { cmd, .. } = &notification_msg {
+                    log::debug!("unknown notification cmd {}", cmd);
+                }
+                Ok(ServerLiveMessage::Notification(notification_msg))
             }
-        };
-        if buff.position() < buff_len as u64 {
-            continue 'start;
-        } else {
-            break 'start;
+            Err(e) => Err(MsgDecodeError::DecodeBodyError(e.to_string())),
+        },
+        8 => Ok(ServerLiveMessage::LoginAck),
+        _ => Err(MsgDecodeError::UndefinedMsg {
+            pkg_v: packet.version,
+            pkg_type: packet.op,
+        }),
+    }
+}
+
+/// 逐条解码一帧数据，见 `DecodeIter`
+pub fn decode_iter(data: Vec<u8>) -> DecodeIter {
+    let mut packets = vec![];
+    let error = split_packets(&data, &mut packets).err();
+    DecodeIter {
+        packets: packets.into_iter(),
+        error,
+        done: false,
+    }
+}
+
+/// 逐条解码一帧数据的迭代器
+///
+/// 单条消息解析失败时返回 `Err` 后继续解码后面的消息；
+/// 包头或解压出错时，先返回出错之前的消息，再返回 `Err` 后结束。
+#[derive(Debug)]
+pub struct DecodeIter {
+    packets: std::vec::IntoIter<Packet>,
+    /// 拆包时遇到的错误，在所有包之后返回
+    error: Option<MsgDecodeError>,
+    done: bool,
+}
+
+impl Iterator for DecodeIter {
+    type Item = Result<ServerLiveMessage, MsgDecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
         }
+        if let Some(packet) = self.packets.next() {
+            return Some(parse_packet(&packet));
+        }
+        self.done = true;
+        self.error.take().map(Err)
     }
-    Ok(())
 }
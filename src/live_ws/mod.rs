@@ -1,65 +1,483 @@
+mod coalesce;
 pub mod message;
+pub mod options;
+mod outbox;
+mod transport;
 
-use crate::api::{APIClient, APIResult, LiveHost};
-use futures_util::stream::{SplitSink, SplitStream};
-use futures_util::{SinkExt, StreamExt};
-pub use message::notification_msg::NotificationMsg;
+use crate::api::{APIClient, DanmuInfoResult, LiveHost, RoomId};
+pub use message::notification_msg::{GuardLevel, InteractType, NotificationMsg};
 pub use message::{ClientLiveMessage, MsgDecodeError, ServerLiveMessage, WsLogin};
-use std::collections::LinkedList;
-use std::sync::Arc;
-use tokio::net::TcpStream;
+pub use options::{ConnectOptions, HostOrder, OverflowPolicy, ReconnectPolicy, Transport};
+use outbox::Outbox;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::broadcast;
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::task::JoinHandle;
-use tokio::time::Duration;
-use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::{CancellationToken, DropGuard};
+use transport::{FrameReader, FrameWriter};
 
+use futures_util::Stream;
 use log::{debug, error, info, warn};
 
+/// 直播间消息流
+///
+/// drop 掉 `MsgStream` 时会通知后台的连接任务退出
 #[derive(Debug)]
 pub struct MsgStream {
     pub room_id: u64,
     pub rx: Receiver<ServerLiveMessage>,
+    /// 消息通道的配置，`coalesce` 之类的转发任务沿用
+    channel_capacity: usize,
+    overflow: OverflowPolicy,
+    dropped: Arc<AtomicU64>,
+    cancel: CancellationToken,
+    _cancel_guard: DropGuard,
     _connect_handler: JoinHandle<Result<(), LiveConnectError>>,
 }
 
-type WsStream = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
-type RsStream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+impl MsgStream {
+    /// 通知后台连接任务退出，不等待其结束
+    pub fn shutdown(&self) {
+        self.cancel.cancel();
+    }
+
+    /// 因为消费太慢被丢掉的消息数，见 `OverflowPolicy`
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// 关闭 websocket 并等待后台连接任务结束
+    pub async fn close(self) {
+        let MsgStream {
+            room_id,
+            rx,
+            _cancel_guard,
+            _connect_handler,
+            ..
+        } = self;
+        // 先关闭接收端，连接任务卡在发送消息上时也能退出
+        drop(rx);
+        drop(_cancel_guard);
+        match _connect_handler.await {
+            Ok(r) => info!("[{room_id}] ws closed {:?}", r),
+            Err(e) => error!("[{room_id}] ws task join error {}", e),
+        }
+    }
+
+    /// 等待后台连接任务结束并返回它的结果，不会主动关闭连接
+    ///
+    /// 在 `rx` 返回 `None` 之后调用，用来区分正常关闭和 `RetryTimeout` 之类的错误。
+    /// `rx` 会被 drop，还有消息没读完时连接任务会以 `TxClose` 结束
+    pub async fn finished(self) -> Result<(), LiveConnectError> {
+        let MsgStream {
+            rx,
+            _cancel_guard,
+            _connect_handler,
+            ..
+        } = self;
+        drop(rx);
+        let r = _connect_handler.await;
+        drop(_cancel_guard);
+        r?
+    }
+}
+
+/// 和 `rx.recv()` 一样，连接任务结束并且消息读完后返回 `None`
+///
+/// # Example
+///
+/// ```no_run
+/// # use bilili_rs::live_ws::{MsgStream, ServerLiveMessage};
+/// use futures_util::StreamExt;
+/// # async fn run(stream: MsgStream) {
+/// let mut heartbeats = stream.filter_map(|msg| {
+///     std::future::ready(match msg {
+///         ServerLiveMessage::ServerHeartBeat(popularity) => Some(popularity),
+///         _ => None,
+///     })
+/// });
+/// while let Some(popularity) = heartbeats.next().await {
+///     println!("{popularity}");
+/// }
+/// # }
+/// ```
+impl Stream for MsgStream {
+    type Item = ServerLiveMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// 可以被多个消费者同时订阅的直播间消息流，由 `MsgStream::into_broadcast` 创建
+///
+/// 订阅者处理太慢时会丢掉旧消息，见 `tokio::sync::broadcast`
+#[derive(Debug)]
+pub struct BroadcastMsgStream {
+    pub room_id: u64,
+    tx: broadcast::Sender<Arc<ServerLiveMessage>>,
+    dropped: Arc<AtomicU64>,
+    cancel: CancellationToken,
+    _cancel_guard: DropGuard,
+    _connect_handler: JoinHandle<Result<(), LiveConnectError>>,
+    _forward_handler: JoinHandle<()>,
+}
+
+impl MsgStream {
+    /// 转换成可以多处订阅的消息流，同时返回第一个订阅者，保证不会漏掉开头的消息
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::sync::Arc;
+    /// # use bilili_rs::api::APIClient;
+    /// # async fn run(api_client: Arc<APIClient>) {
+    /// let stream = bilili_rs::live_ws::connect(api_client, 1234, 10);
+    /// let (stream, mut log_rx) = stream.into_broadcast(256);
+    /// let mut ui_rx = stream.subscribe();
+    /// tokio::spawn(async move {
+    ///     while let Ok(msg) = log_rx.recv().await { /* 写入文件 */ }
+    /// });
+    /// while let Ok(msg) = ui_rx.recv().await { /* 刷新界面 */ }
+    /// # }
+    /// ```
+    pub fn into_broadcast(
+        self,
+        capacity: usize,
+    ) -> (
+        BroadcastMsgStream,
+        broadcast::Receiver<Arc<ServerLiveMessage>>,
+    ) {
+        let MsgStream {
+            room_id,
+            mut rx,
+            dropped,
+            cancel,
+            _cancel_guard,
+            _connect_handler,
+            ..
+        } = self;
+        let (tx, first_rx) = broadcast::channel(capacity);
+        let forward_tx = tx.clone();
+        let _forward_handler = tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                // 没有订阅者时直接丢弃
+                let _ = forward_tx.send(Arc::new(msg));
+            }
+        });
+        let stream = BroadcastMsgStream {
+            room_id,
+            tx,
+            dropped,
+            cancel,
+            _cancel_guard,
+            _connect_handler,
+            _forward_handler,
+        };
+        (stream, first_rx)
+    }
+}
+
+impl BroadcastMsgStream {
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<ServerLiveMessage>> {
+        self.tx.subscribe()
+    }
+
+    /// 连接任务因为转发太慢丢掉的消息数，不包含订阅者 `Lagged` 丢掉的消息
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// 通知后台连接任务退出，不等待其结束
+    pub fn shutdown(&self) {
+        self.cancel.cancel();
+    }
+
+    /// 关闭 websocket 并等待后台连接任务结束
+    pub async fn close(self) {
+        let BroadcastMsgStream {
+            room_id,
+            tx,
+            _cancel_guard,
+            _connect_handler,
+            _forward_handler,
+            ..
+        } = self;
+        drop(tx);
+        drop(_cancel_guard);
+        match _connect_handler.await {
+            Ok(r) => info!("[{room_id}] ws closed {:?}", r),
+            Err(e) => error!("[{room_id}] ws task join error {}", e),
+        }
+        let _ = _forward_handler.await;
+    }
+
+    /// 等待后台连接任务结束并返回它的结果，见 `MsgStream::finished`
+    pub async fn finished(self) -> Result<(), LiveConnectError> {
+        let BroadcastMsgStream {
+            tx,
+            _cancel_guard,
+            _connect_handler,
+            _forward_handler,
+            ..
+        } = self;
+        drop(tx);
+        let r = _connect_handler.await;
+        drop(_cancel_guard);
+        let _ = _forward_handler.await;
+        r?
+    }
+}
 
 // const BILI_CHAT_SERVER_URL: &'static str = "wss://broadcastlv.chat.bilibili.com/sub";
 
 pub fn connect(api_client: Arc<APIClient>, room_id: u64, max_retry: u32) -> MsgStream {
+    connect_with(api_client, room_id, ConnectOptions::new(max_retry))
+}
+
+/// 同 `connect`，可以自定义重连策略等配置
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::{sync::Arc, time::Duration};
+/// # use bilili_rs::api::APIClient;
+/// # use bilili_rs::live_ws::{connect_with, ConnectOptions, ReconnectPolicy};
+/// # async fn run(api_client: Arc<APIClient>) {
+/// let opts = ConnectOptions {
+///     reconnect: ReconnectPolicy {
+///         base: Duration::from_secs(1),
+///         factor: 2.0,
+///         jitter: true,
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// let mut stream = connect_with(api_client, 1234, opts);
+/// while let Some(msg) = stream.rx.recv().await { /* ... */ }
+/// # }
+/// ```
+pub fn connect_with(api_client: Arc<APIClient>, room_id: u64, opts: ConnectOptions) -> MsgStream {
     // let url = BILI_CHAT_SERVER_URL.parse().unwrap();
 
     info!("[{room_id}] ws start connect");
 
-    let (tx, rx) = tokio::sync::mpsc::channel(64);
-    let _connect_handler = tokio::spawn(open_client(api_client, room_id, tx, max_retry));
+    let (tx, rx) = tokio::sync::mpsc::channel(opts.channel_capacity.max(1));
+    let dropped = Arc::new(AtomicU64::new(0));
+    let cancel = CancellationToken::new();
+    let channel_capacity = opts.channel_capacity;
+    let overflow = opts.overflow;
+    let _connect_handler = tokio::spawn(run_client(
+        api_client,
+        room_id,
+        tx,
+        opts,
+        cancel.clone(),
+        dropped.clone(),
+    ));
     MsgStream {
         room_id,
         rx,
+        channel_capacity,
+        overflow,
+        dropped,
+        _cancel_guard: cancel.clone().drop_guard(),
+        cancel,
         _connect_handler,
     }
 }
 
-async fn open_bili_ws(
+/// 同 `connect_with`，每收到一条消息调用一次 `handler`，不需要自己读取消息通道
+///
+/// 返回的任务在连接结束后完成，结果同 `MsgStream::finished`；abort 这个任务会关闭连接。
+/// `handler` 在后台任务里执行，耗时的处理会阻塞后面的消息
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use bilili_rs::api::APIClient;
+/// # use bilili_rs::live_ws::{connect_with_handler, ConnectOptions, NotificationMsg, ServerLiveMessage};
+/// # async fn run(api_client: Arc<APIClient>) {
+/// let handle = connect_with_handler(api_client, 1234, ConnectOptions::new(10), |msg| {
+///     if let ServerLiveMessage::Notification(NotificationMsg::DANMU_MSG { info }) = msg {
+///         println!("{}: {}", info.uname, info.text);
+///     }
+/// });
+/// handle.await.unwrap().unwrap();
+/// # }
+/// ```
+pub fn connect_with_handler<F>(
+    api_client: Arc<APIClient>,
     room_id: u64,
-    sub_urls: &[LiveHost],
-) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, tokio_tungstenite::tungstenite::Error> {
-    let mut err = None;
-    for host in sub_urls {
-        let url = format!("wss://{}/sub", host.host);
-        let connect_r = connect_async(&url).await;
-        match connect_r {
-            Ok((ws_stream, _)) => return Ok(ws_stream),
-            Err(e) => {
-                error!("ws connect [{room_id}] to {url} error {:?}", e);
-                err = Some(e);
-                continue;
+    opts: ConnectOptions,
+    mut handler: F,
+) -> JoinHandle<Result<(), LiveConnectError>>
+where
+    F: FnMut(ServerLiveMessage) + Send + 'static,
+{
+    let mut stream = connect_with(api_client, room_id, opts);
+    tokio::spawn(async move {
+        while let Some(msg) = stream.rx.recv().await {
+            handler(msg);
+        }
+        stream.finished().await
+    })
+}
+
+/// 多个直播间合并的消息流，每个直播间仍然使用独立的 websocket 连接
+///
+/// drop 掉 `MultiMsgStream` 时会通知所有后台连接任务退出
+#[derive(Debug)]
+pub struct MultiMsgStream {
+    pub rx: Receiver<(u64, ServerLiveMessage)>,
+    tx: Sender<(u64, ServerLiveMessage)>,
+    api_client: Arc<APIClient>,
+    opts: ConnectOptions,
+    dropped: Arc<AtomicU64>,
+    rooms: HashMap<u64, (tokio::task::Id, CancellationToken)>,
+    cancel: CancellationToken,
+    _cancel_guard: DropGuard,
+    tasks: JoinSet<(u64, Result<(), LiveConnectError>)>,
+}
+
+pub fn connect_many(
+    api_client: Arc<APIClient>,
+    room_ids: &[u64],
+    max_retry: u32,
+) -> MultiMsgStream {
+    connect_many_with(api_client, room_ids, ConnectOptions::new(max_retry))
+}
+
+/// 同 `connect_many`，所有直播间共用同一份配置
+///
+/// 合并后的消息通道和每个直播间自己的通道容量都是 `channel_capacity`，
+/// 消费太慢时按 `overflow` 丢弃各个直播间的消息
+pub fn connect_many_with(
+    api_client: Arc<APIClient>,
+    room_ids: &[u64],
+    opts: ConnectOptions,
+) -> MultiMsgStream {
+    let (tx, rx) = tokio::sync::mpsc::channel(opts.channel_capacity.max(1));
+    let cancel = CancellationToken::new();
+    let mut stream = MultiMsgStream {
+        rx,
+        tx,
+        api_client,
+        opts,
+        dropped: Arc::new(AtomicU64::new(0)),
+        rooms: HashMap::new(),
+        _cancel_guard: cancel.clone().drop_guard(),
+        cancel,
+        tasks: JoinSet::new(),
+    };
+    for room_id in room_ids {
+        stream.add_room(*room_id);
+    }
+    stream
+}
+
+/// 同 `MsgStream`，消息带着来源的直播间号
+impl Stream for MultiMsgStream {
+    type Item = (u64, ServerLiveMessage);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+impl MultiMsgStream {
+    /// 订阅一个新的直播间，已经订阅的直播间会被忽略
+    pub fn add_room(&mut self, room_id: u64) -> bool {
+        self.reap_finished();
+        if self.rooms.contains_key(&room_id) {
+            return false;
+        }
+
+        info!("[{room_id}] ws start connect");
+        let cancel = self.cancel.child_token();
+        let room_cancel = cancel.clone();
+
+        let api_client = self.api_client.clone();
+        let opts = self.opts.clone();
+        let tx = self.tx.clone();
+        let dropped = self.dropped.clone();
+        let handle = self.tasks.spawn(async move {
+            let (room_tx, mut room_rx) = tokio::sync::mpsc::channel(opts.channel_capacity.max(1));
+            let forward = async move {
+                while let Some(msg) = room_rx.recv().await {
+                    if tx.send((room_id, msg)).await.is_err() {
+                        break;
+                    }
+                }
+            };
+            let (r, _) = tokio::join!(
+                run_client(api_client, room_id, room_tx, opts, cancel, dropped),
+                forward
+            );
+            (room_id, r)
+        });
+        self.rooms.insert(room_id, (handle.id(), room_cancel));
+        true
+    }
+
+    /// 取消订阅一个直播间，返回该直播间之前是否在订阅中
+    pub fn remove_room(&mut self, room_id: u64) -> bool {
+        match self.rooms.remove(&room_id) {
+            Some((_, cancel)) => {
+                cancel.cancel();
+                true
             }
-        };
+            None => false,
+        }
+    }
+
+    /// 当前仍在连接中的直播间
+    pub fn room_ids(&mut self) -> Vec<u64> {
+        self.reap_finished();
+        self.rooms.keys().copied().collect()
+    }
+
+    /// 所有直播间因为消费太慢被丢掉的消息数，见 `OverflowPolicy`
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// 通知所有后台连接任务退出，不等待其结束
+    pub fn shutdown(&self) {
+        self.cancel.cancel();
+    }
+
+    /// 关闭所有 websocket 并等待后台连接任务结束
+    pub async fn close(mut self) {
+        self.cancel.cancel();
+        while let Some(r) = self.tasks.join_next().await {
+            match r {
+                Ok((room_id, r)) => info!("[{room_id}] ws closed {:?}", r),
+                Err(e) => error!("ws task join error {}", e),
+            }
+        }
+    }
+
+    fn reap_finished(&mut self) {
+        while let Some(r) = self.tasks.try_join_next_with_id() {
+            match r {
+                Ok((id, (room_id, r))) => {
+                    info!("[{room_id}] ws closed {:?}", r);
+                    // 同一个直播间可能在旧任务结束前被重新订阅
+                    if self.rooms.get(&room_id).is_some_and(|(i, _)| *i == id) {
+                        self.rooms.remove(&room_id);
+                    }
+                }
+                Err(e) => error!("ws task join error {}", e),
+            }
+        }
     }
-    Err(err.unwrap())
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -68,8 +486,16 @@ pub enum LiveConnectError {
     TxClose,
     #[error("IO: {0}")]
     IoError(#[from] tokio_tungstenite::tungstenite::Error),
+    #[error("TcpIO: {0}")]
+    TcpIoError(#[from] std::io::Error),
+    #[error("NoHost")]
+    NoHost,
     #[error("RetryTimeout")]
     RetryTimeout,
+    #[error("HeartbeatTimeout")]
+    HeartbeatTimeout,
+    #[error("TaskJoinError {0}")]
+    TaskJoinError(#[from] tokio::task::JoinError),
 }
 
 pub async fn open_client(
@@ -78,118 +504,330 @@ pub async fn open_client(
     tx: Sender<ServerLiveMessage>,
     max_retry: u32,
 ) -> Result<(), LiveConnectError> {
+    open_client_with(api_client, room_id, tx, ConnectOptions::new(max_retry)).await
+}
+
+pub async fn open_client_with(
+    api_client: Arc<APIClient>,
+    room_id: u64,
+    tx: Sender<ServerLiveMessage>,
+    opts: ConnectOptions,
+) -> Result<(), LiveConnectError> {
+    let dropped = Arc::new(AtomicU64::new(0));
+    run_client(
+        api_client,
+        room_id,
+        tx,
+        opts,
+        CancellationToken::new(),
+        dropped,
+    )
+    .await
+}
+
+async fn run_client(
+    api_client: Arc<APIClient>,
+    room_id: u64,
+    tx: Sender<ServerLiveMessage>,
+    opts: ConnectOptions,
+    cancel: CancellationToken,
+    dropped: Arc<AtomicU64>,
+) -> Result<(), LiveConnectError> {
+    let mut outbox = Outbox::new(tx, opts.overflow, opts.channel_capacity, dropped);
     let uid = api_client.token.uid.parse().unwrap();
     let mut reconnect_time = 0u32;
+    let mut cached_info: Option<(Instant, DanmuInfoResult)> = None;
+    // `TcpThenWss` 时 tcp 出过错，之后的重连直接使用 wss
+    let mut tcp_failed = false;
     'a: loop {
-        if reconnect_time >= max_retry {
-            error!("reconnect [{room_id}] fail");
+        if cancel.is_cancelled() {
+            return Ok(());
+        }
+        if reconnect_time >= opts.max_retry {
+            error!("[{room_id}] reconnect fail after {reconnect_time} attempts");
             return Err(LiveConnectError::RetryTimeout);
         }
-        reconnect_time = reconnect_time + 1;
+        reconnect_time += 1;
+        let tag = ConnTag {
+            room_id,
+            attempt: reconnect_time,
+        };
         let start_time = std::time::SystemTime::now();
-        let danmu_info = api_client.get_danmu_info(room_id).await;
-        let info = match danmu_info {
-            Ok(info) => info,
+        // 短号需要换成真实房间号才能登录，结果有缓存
+        let real_room_id = match api_client.resolve_room_id(room_id).await {
+            Ok(id) => id,
             Err(e) => {
-                error!("get [{room_id}] danmu info {}", e);
+                error!("{tag} resolve room id {}", e);
+                if !reconnect_wait(tag, opts.reconnect.delay(reconnect_time), &cancel).await {
+                    return Ok(());
+                }
                 continue 'a;
             }
         };
+        // 短时间内断线重连时，上一次的 token 和服务器列表通常仍然可用
+        let (mut info, from_cache) = match cached_info.take() {
+            Some((fetched_at, info)) if fetched_at.elapsed() < opts.reconnect.reset_after => {
+                (info, true)
+            }
+            _ if opts.token.is_some() => {
+                let info = DanmuInfoResult {
+                    token: opts.token.clone().unwrap_or_default(),
+                    host_list: vec![fallback_host()],
+                    ..Default::default()
+                };
+                (info, false)
+            }
+            _ => {
+                let danmu_info = api_client.get_danmu_info(RoomId::Real(real_room_id)).await;
+                let info = match danmu_info {
+                    Ok(info) => info,
+                    Err(e) => {
+                        error!("{tag} get danmu info {}", e);
+                        if !reconnect_wait(tag, opts.reconnect.delay(reconnect_time), &cancel).await
+                        {
+                            return Ok(());
+                        }
+                        continue 'a;
+                    }
+                };
 
-        let info = if let APIResult {
-            code: 0,
-            data: Some(info),
-            ..
-        } = info
-        {
-            info
-        } else {
-            error!("get [{room_id}] danmu info {:?}", info);
-            continue 'a;
+                let info = match info.into_result() {
+                    Ok(info) => info,
+                    Err(e) => {
+                        error!("{tag} get danmu info {}", e);
+                        // 被风控时至少等待接口建议的时间
+                        let delay = opts.reconnect.delay(reconnect_time);
+                        let delay = e.retry_after().map_or(delay, |d| d.max(delay));
+                        if !reconnect_wait(tag, delay, &cancel).await {
+                            return Ok(());
+                        }
+                        continue 'a;
+                    }
+                };
+                (info, false)
+            }
         };
 
+        if let Some(hosts) = &opts.hosts {
+            info.host_list = hosts.clone();
+        }
+
         let ws_login = WsLogin {
-            room_id,
+            room_id: real_room_id,
             uid,
-            key: info.token,
+            key: info.token.clone(),
         };
 
-        let ws_stream = open_bili_ws(room_id, &info.host_list).await?;
-        let (mut w_stream, mut r_stream) = ws_stream.split();
-        let r = tokio::try_join!(
-            connect_keep(&mut w_stream, ws_login),
-            loop_handle_msg(&mut r_stream, tx.clone())
-        );
-        info!("ws client close [{room_id}] {:?}", r);
+        let (mut w_stream, mut r_stream) =
+            match transport::open_transport(tag, &info.host_list, &opts, tcp_failed).await {
+                Ok(r) => r,
+                Err(e) => {
+                    if from_cache {
+                        // 下一次会重新获取 token 和服务器列表
+                        warn!("{tag} connect with cached danmu info fail {}, refetch", e);
+                    } else {
+                        error!("{tag} open transport {}", e);
+                    }
+                    if !reconnect_wait(tag, opts.reconnect.delay(reconnect_time), &cancel).await {
+                        return Ok(());
+                    }
+                    continue 'a;
+                }
+            };
+        let via_tcp = w_stream.is_tcp();
+        if opts.transport == Transport::TcpThenWss && !via_tcp && !tcp_failed {
+            info!("{tag} tcp unavailable, use wss from now on");
+            tcp_failed = true;
+        }
+        let state = ConnState::new(tag);
+        let r = tokio::select! {
+            r = async {
+                tokio::try_join!(
+                    connect_keep(&mut w_stream, ws_login, &opts, &state),
+                    loop_handle_msg(&mut r_stream, &mut outbox, &state)
+                )
+            } => r,
+            _ = cancel.cancelled() => {
+                info!("{tag} ws client shutdown");
+                w_stream.close().await;
+                return Ok(());
+            }
+        };
+        // 没有登录成功说明 token 可能已经失效，下次重新获取
+        if state.login_acked.load(Ordering::Relaxed) {
+            cached_info = Some((Instant::now(), info));
+        } else if via_tcp {
+            info!("{tag} tcp closed before login, use wss from now on");
+            tcp_failed = true;
+        }
+        info!("{tag} ws client close {:?}", r);
         if let Err(LiveConnectError::TxClose) = r {
             return Err(LiveConnectError::TxClose);
         }
+        // 消费者不再读取时通道可能是满的，发送要能被取消
+        tokio::select! {
+            r = outbox.send_control(ServerLiveMessage::Disconnected) => r?,
+            _ = cancel.cancelled() => {
+                info!("{tag} ws client shutdown");
+                return Ok(());
+            }
+        }
         let now = std::time::SystemTime::now();
-        let d = now.duration_since(start_time).unwrap().as_secs();
-        if d > (60 * 30) {
+        let d = now.duration_since(start_time).unwrap_or_default();
+        if d > opts.reconnect.reset_after {
             reconnect_time = 0;
         }
-        let time = if reconnect_time <= 20 { 10 } else { 300 };
-        info!("reconnect [{room_id}] [{reconnect_time}] after {time} secs");
-        tokio::time::sleep(Duration::from_secs(time)).await;
-        info!("reconnect [{room_id}] start");
+        tokio::select! {
+            r = outbox.send_control(ServerLiveMessage::Reconnecting {
+                attempt: reconnect_time,
+            }) => r?,
+            _ = cancel.cancelled() => {
+                info!("{tag} ws client shutdown");
+                return Ok(());
+            }
+        }
+        if !reconnect_wait(tag, opts.reconnect.delay(reconnect_time.max(1)), &cancel).await {
+            return Ok(());
+        }
+        info!("{tag} reconnect start");
+    }
+}
+
+/// 重连前等待 `delay`，期间被取消时返回 `false`
+async fn reconnect_wait(tag: ConnTag, delay: Duration, cancel: &CancellationToken) -> bool {
+    info!("{tag} reconnect after {delay:?}");
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => true,
+        _ = cancel.cancelled() => {
+            info!("{tag} ws client shutdown");
+            false
+        }
+    }
+}
+
+/// `getDanmuInfo` 不可用时使用的弹幕服务器
+fn fallback_host() -> LiveHost {
+    LiveHost {
+        host: "broadcastlv.chat.bilibili.com".to_string(),
+        port: 2243,
+        ws_port: 2244,
+        wss_port: 443,
     }
 }
 
-async fn connect_keep(client: &mut WsStream, ws_login: WsLogin) -> Result<(), LiveConnectError> {
+/// 日志前缀 `[房间号#第几次连接]`，同时连接多个直播间时用来区分日志
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ConnTag {
+    pub room_id: u64,
+    pub attempt: u32,
+}
+
+impl std::fmt::Display for ConnTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}#{}]", self.room_id, self.attempt)
+    }
+}
+
+/// 一次 websocket 连接期间两个任务共享的状态
+struct ConnState {
+    tag: ConnTag,
+    last_heartbeat: Mutex<Instant>,
+    login_acked: AtomicBool,
+    /// 下一个发出的包的序号，每个连接从 1 开始
+    seq: AtomicU32,
+}
+
+impl ConnState {
+    fn new(tag: ConnTag) -> Self {
+        Self {
+            tag,
+            last_heartbeat: Mutex::new(Instant::now()),
+            login_acked: AtomicBool::new(false),
+            seq: AtomicU32::new(1),
+        }
+    }
+
+    fn next_seq(&self) -> u32 {
+        self.seq.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+async fn connect_keep(
+    client: &mut FrameWriter,
+    ws_login: WsLogin,
+    opts: &ConnectOptions,
+    state: &ConnState,
+) -> Result<(), LiveConnectError> {
     client
-        .send(Message::Binary(ClientLiveMessage::Login(ws_login).encode()))
+        .send(ClientLiveMessage::Login(ws_login).encode_with_seq(state.next_seq()))
         .await?;
+    let timeout = opts.heartbeat_interval * opts.heartbeat_timeout;
     loop {
-        debug!("heartbeat");
+        debug!("{} heartbeat", state.tag);
         client
-            .send(Message::Binary(ClientLiveMessage::ClientHeartBeat.encode()))
+            .send(ClientLiveMessage::ClientHeartBeat.encode_with_seq(state.next_seq()))
             .await?;
-        tokio::time::sleep(Duration::from_secs(30)).await;
+        tokio::time::sleep(opts.heartbeat_interval).await;
+        let elapsed = state.last_heartbeat.lock().unwrap().elapsed();
+        if opts.heartbeat_timeout > 0 && elapsed > timeout {
+            warn!("{} no heartbeat reply for {:?}", state.tag, elapsed);
+            return Err(LiveConnectError::HeartbeatTimeout);
+        }
     }
 }
 
 async fn loop_handle_msg(
-    client: &mut RsStream,
-    tx: Sender<ServerLiveMessage>,
+    client: &mut FrameReader,
+    outbox: &mut Outbox,
+    state: &ConnState,
 ) -> Result<(), LiveConnectError> {
-    let mut msg_list = LinkedList::new();
-    while let Some(msg) = client.next().await {
-        let msg = msg?;
-        match msg {
-            Message::Text(text) => {
-                debug!("recv text {}", text)
+    let mut msg_list = Vec::new();
+    let mut errors = Vec::new();
+    loop {
+        let bin = tokio::select! {
+            bin = client.next() => bin,
+            // 有积压的消息时，通道一有空位就发出去
+            permit = outbox.tx.reserve(), if !outbox.pending.is_empty() => {
+                let permit = permit.map_err(|_| LiveConnectError::TxClose)?;
+                if let Some(msg) = outbox.pending.pop_front() {
+                    permit.send(msg);
+                }
+                continue;
             }
-            Message::Binary(bin) => {
-                if let Err(e) = message::decode_from_server(bin, &mut msg_list) {
-                    warn!("handler msg {:?}", e)
+        };
+        let Some(bin) = bin else {
+            break;
+        };
+        let bin = bin?;
+        if let Err(e) = message::decode_from_server(bin, &mut msg_list, &mut errors) {
+            warn!("{} handler msg {:?}", state.tag, e)
+        }
+        for e in errors.drain(..) {
+            warn!("{} decode msg {:?}", state.tag, e)
+        }
+        for msg in msg_list.drain(..) {
+            match &msg {
+                ServerLiveMessage::LoginAck => {
+                    debug!("{} LoginAck", state.tag);
+                    state.login_acked.store(true, Ordering::Relaxed);
+                    outbox.send_control(ServerLiveMessage::Connected).await?;
                 }
-                while let Some(msg) = msg_list.pop_front() {
-                    match &msg {
-                        ServerLiveMessage::LoginAck => {
-                            debug!("LoginAck");
-                        }
-                        ServerLiveMessage::Notification(_) => {
-                            debug!("Notification");
-                        }
-                        ServerLiveMessage::ServerHeartBeat => {
-                            debug!("ServerHeartBeat");
-                        }
-                    }
-                    tx.send(msg).await.map_err(|_| LiveConnectError::TxClose)?;
-                    debug!("send msg ok");
+                ServerLiveMessage::Notification(_) => {
+                    debug!("{} Notification", state.tag);
                 }
+                ServerLiveMessage::ServerHeartBeat(popularity) => {
+                    debug!("{} ServerHeartBeat popularity={popularity}", state.tag);
+                    *state.last_heartbeat.lock().unwrap() = Instant::now();
+                }
+                ServerLiveMessage::Connected
+                | ServerLiveMessage::Reconnecting { .. }
+                | ServerLiveMessage::Disconnected => {}
             }
-            Message::Ping(_) => debug!("ws ping"),
-            Message::Pong(_) => debug!("ws pong"),
-            Message::Close(_) => {
-                warn!("ws close");
-                break;
-            }
-            Message::Frame(_) => warn!("ws frame (unreachable)"),
+            outbox.send(msg).await?;
+            debug!("{} send msg ok", state.tag);
         }
     }
-    warn!("ws handle loop stop");
+    warn!("{} ws handle loop stop", state.tag);
     Err(LiveConnectError::IoError(
         tokio_tungstenite::tungstenite::Error::ConnectionClosed,
     ))
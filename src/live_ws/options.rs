@@ -0,0 +1,157 @@
+use rand::{seq::SliceRandom, Rng};
+use tokio::time::Duration;
+
+use crate::api::LiveHost;
+
+/// 断线重连的等待策略
+///
+/// 第 `attempt` 次重连前等待 `min(max, base * factor ^ (attempt - 1))`，
+/// 超过 `max_after` 次之后直接等待 `max`，
+/// 开启 `jitter` 时再加上最多一半的随机时间，避免大量连接同时重连。
+#[derive(Debug, Clone, Copy)]
+pub struct ReconnectPolicy {
+    pub base: Duration,
+    pub max: Duration,
+    pub factor: f64,
+    /// 连续重连超过这个次数后每次都等待 `max`，0 表示不限制
+    pub max_after: u32,
+    pub jitter: bool,
+    /// 连接保持超过这个时间后，重连计数清零
+    pub reset_after: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    /// 前 20 次等待 10 秒，之后每次等待 5 分钟，连接保持 30 分钟后重连计数清零
+    fn default() -> Self {
+        Self {
+            base: Duration::from_secs(10),
+            max: Duration::from_secs(300),
+            factor: 1.0,
+            max_after: 20,
+            jitter: false,
+            reset_after: Duration::from_secs(60 * 30),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 第 `attempt` 次重连前的等待时间，`attempt` 从 1 开始
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.base.as_secs_f64() * self.factor.powi(exp);
+        let capped = self.max_after > 0 && attempt > self.max_after;
+        let delay = if !capped && secs.is_finite() && secs < self.max.as_secs_f64() {
+            Duration::from_secs_f64(secs)
+        } else {
+            self.max
+        };
+        if self.jitter && !delay.is_zero() {
+            delay + delay.mul_f64(rand::thread_rng().gen_range(0.0..0.5))
+        } else {
+            delay
+        }
+    }
+}
+
+/// 直播间 websocket 连接的配置
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    /// 最多连接次数，超过后返回 `LiveConnectError::RetryTimeout`
+    pub max_retry: u32,
+    pub reconnect: ReconnectPolicy,
+    /// 心跳包间隔
+    pub heartbeat_interval: Duration,
+    /// 连续这么多个心跳间隔都没有收到服务端的心跳回复时，认为连接已经断开并重连，0 表示不检查
+    pub heartbeat_timeout: u32,
+    pub host_order: HostOrder,
+    pub transport: Transport,
+    /// 指定弹幕服务器，代替 `get_danmu_info` 返回的服务器列表
+    pub hosts: Option<Vec<LiveHost>>,
+    /// 指定登录用的 token，设置后不再请求 `get_danmu_info`，
+    /// 这时没有设置 `hosts` 会使用 `broadcastlv.chat.bilibili.com`。
+    /// token 和直播间绑定，同时连接多个直播间时不要设置
+    pub token: Option<String>,
+    /// 消息通道的容量
+    pub channel_capacity: usize,
+    /// 通道满了之后的处理方式
+    pub overflow: OverflowPolicy,
+    /// 单条消息的最大字节数，`None` 表示不限制，超过后连接会断开。
+    /// 使用 tcp 时为单个包的最大长度
+    pub max_message_size: Option<usize>,
+    /// 单个 websocket 帧的最大字节数，`None` 表示不限制
+    pub max_frame_size: Option<usize>,
+}
+
+/// 消费者处理太慢、消息通道满了之后的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// 等待消费者，这期间不会读取 websocket，时间长了可能导致断线
+    #[default]
+    Block,
+    /// 丢掉最早的消息，最多另外积压 `channel_capacity` 条
+    DropOldest,
+    /// 丢掉新收到的消息
+    DropNewest,
+}
+
+/// 连接弹幕服务器使用的协议
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Transport {
+    /// `wss://host:wss_port/sub`
+    #[default]
+    WssOnly,
+    /// `ws://host:ws_port/sub`
+    WsOnly,
+    /// 先尝试直接用 tcp 连接 `host:port`，全部失败后再尝试 wss
+    ///
+    /// 网络屏蔽了 websocket 时 tcp 通常仍然可用。tcp 连不上或者登录前就断开后，
+    /// 之后的重连都直接使用 wss
+    TcpThenWss,
+}
+
+/// 依次尝试 `get_danmu_info` 返回的服务器时的顺序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HostOrder {
+    /// 按接口返回的顺序
+    #[default]
+    InOrder,
+    /// 打乱顺序，让大量连接分散到不同的服务器上
+    Shuffle,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            max_retry: u32::MAX,
+            reconnect: ReconnectPolicy::default(),
+            heartbeat_interval: Duration::from_secs(30),
+            heartbeat_timeout: 3,
+            host_order: HostOrder::default(),
+            transport: Transport::default(),
+            hosts: None,
+            token: None,
+            channel_capacity: 64,
+            overflow: OverflowPolicy::default(),
+            // 人多的直播间一个压缩包里可能有上千条消息，默认值给得宽松一些
+            max_message_size: Some(64 << 20),
+            max_frame_size: Some(64 << 20),
+        }
+    }
+}
+
+impl ConnectOptions {
+    pub fn new(max_retry: u32) -> Self {
+        Self {
+            max_retry,
+            ..Default::default()
+        }
+    }
+}
+
+impl HostOrder {
+    pub(crate) fn apply<T>(&self, hosts: &mut [T]) {
+        if *self == HostOrder::Shuffle {
+            hosts.shuffle(&mut rand::thread_rng());
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::Sender;
+
+use super::{LiveConnectError, OverflowPolicy, ServerLiveMessage};
+
+/// 按 `OverflowPolicy` 把消息发给消费者，避免消费者太慢时卡住 websocket 的读取
+pub(crate) struct Outbox {
+    pub(crate) tx: Sender<ServerLiveMessage>,
+    policy: OverflowPolicy,
+    capacity: usize,
+    /// `DropOldest` 时通道满了之后积压的消息
+    pub(crate) pending: VecDeque<ServerLiveMessage>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Outbox {
+    pub(crate) fn new(
+        tx: Sender<ServerLiveMessage>,
+        policy: OverflowPolicy,
+        capacity: usize,
+        dropped: Arc<AtomicU64>,
+    ) -> Self {
+        Self {
+            tx,
+            policy,
+            capacity: capacity.max(1),
+            pending: VecDeque::new(),
+            dropped,
+        }
+    }
+
+    pub(crate) async fn send(&mut self, msg: ServerLiveMessage) -> Result<(), LiveConnectError> {
+        match self.policy {
+            OverflowPolicy::Block => self
+                .tx
+                .send(msg)
+                .await
+                .map_err(|_| LiveConnectError::TxClose),
+            OverflowPolicy::DropNewest => match self.tx.try_send(msg) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    Ok(())
+                }
+                Err(TrySendError::Closed(_)) => Err(LiveConnectError::TxClose),
+            },
+            OverflowPolicy::DropOldest => {
+                self.pending.push_back(msg);
+                if self.pending.len() > self.capacity {
+                    self.pending.pop_front();
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                if self.flush_ready() {
+                    Ok(())
+                } else {
+                    Err(LiveConnectError::TxClose)
+                }
+            }
+        }
+    }
+
+    /// 不会被丢弃的消息，先发出积压的消息保证顺序
+    pub(crate) async fn send_control(
+        &mut self,
+        msg: ServerLiveMessage,
+    ) -> Result<(), LiveConnectError> {
+        self.flush().await?;
+        self.tx
+            .send(msg)
+            .await
+            .map_err(|_| LiveConnectError::TxClose)
+    }
+
+    /// 不等待，把积压的消息尽量发出去，消费者已经关闭时返回 `false`
+    fn flush_ready(&mut self) -> bool {
+        while let Some(msg) = self.pending.pop_front() {
+            match self.tx.try_send(msg) {
+                Ok(()) => {}
+                Err(TrySendError::Full(msg)) => {
+                    self.pending.push_front(msg);
+                    break;
+                }
+                Err(TrySendError::Closed(_)) => return false,
+            }
+        }
+        true
+    }
+
+    /// 等待积压的消息全部发出
+    pub(crate) async fn flush(&mut self) -> Result<(), LiveConnectError> {
+        while let Some(msg) = self.pending.pop_front() {
+            self.tx
+                .send(msg)
+                .await
+                .map_err(|_| LiveConnectError::TxClose)?;
+        }
+        Ok(())
+    }
+}
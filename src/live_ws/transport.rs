@@ -0,0 +1,156 @@
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, error, info, warn};
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async_with_config, MaybeTlsStream, WebSocketStream};
+use tokio_util::codec::{FramedRead, LengthDelimitedCodec};
+
+use super::{ConnTag, ConnectOptions, LiveConnectError, Transport};
+use crate::api::LiveHost;
+
+type WsStream = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+type RsStream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+
+/// 发送数据帧，websocket 和 tcp 的帧格式相同
+pub(crate) enum FrameWriter {
+    Ws(WsStream),
+    Tcp(OwnedWriteHalf),
+}
+
+impl FrameWriter {
+    pub(crate) async fn send(&mut self, data: Vec<u8>) -> Result<(), LiveConnectError> {
+        match self {
+            FrameWriter::Ws(w) => w.send(Message::Binary(data)).await?,
+            FrameWriter::Tcp(w) => w.write_all(&data).await?,
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_tcp(&self) -> bool {
+        matches!(self, FrameWriter::Tcp(_))
+    }
+
+    pub(crate) async fn close(&mut self) {
+        let _ = match self {
+            FrameWriter::Ws(w) => w.close().await.map_err(LiveConnectError::from),
+            FrameWriter::Tcp(w) => w.shutdown().await.map_err(LiveConnectError::from),
+        };
+    }
+}
+
+/// 接收完整的数据帧，包含 16 字节的包头
+pub(crate) enum FrameReader {
+    Ws(RsStream),
+    Tcp(FramedRead<OwnedReadHalf, LengthDelimitedCodec>),
+}
+
+impl FrameReader {
+    /// 连接关闭时返回 `None`
+    pub(crate) async fn next(&mut self) -> Option<Result<Vec<u8>, LiveConnectError>> {
+        match self {
+            FrameReader::Ws(r) => loop {
+                let msg = match r.next().await? {
+                    Ok(msg) => msg,
+                    Err(e) => return Some(Err(e.into())),
+                };
+                match msg {
+                    Message::Text(text) => debug!("recv text {}", text),
+                    Message::Binary(bin) => return Some(Ok(bin)),
+                    Message::Ping(_) => debug!("ws ping"),
+                    Message::Pong(_) => debug!("ws pong"),
+                    Message::Close(frame) => {
+                        debug!("ws close {:?}", frame);
+                        return None;
+                    }
+                    Message::Frame(_) => warn!("ws frame (unreachable)"),
+                }
+            },
+            FrameReader::Tcp(r) => match r.next().await? {
+                Ok(frame) => Some(Ok(frame.to_vec())),
+                Err(e) => Some(Err(e.into())),
+            },
+        }
+    }
+}
+
+async fn open_ws(
+    url: &str,
+    opts: &ConnectOptions,
+) -> Result<(FrameWriter, FrameReader), LiveConnectError> {
+    let config = WebSocketConfig {
+        max_message_size: opts.max_message_size,
+        max_frame_size: opts.max_frame_size,
+        ..Default::default()
+    };
+    let (ws_stream, _) = connect_async_with_config(url, Some(config), false).await?;
+    let (w, r) = ws_stream.split();
+    Ok((FrameWriter::Ws(w), FrameReader::Ws(r)))
+}
+
+async fn open_tcp(
+    addr: &str,
+    opts: &ConnectOptions,
+) -> Result<(FrameWriter, FrameReader), LiveConnectError> {
+    let stream = TcpStream::connect(addr).await?;
+    let (r, w) = stream.into_split();
+    // 包头的第一个字段就是整个包的长度（包含包头）
+    let codec = LengthDelimitedCodec::builder()
+        .length_field_offset(0)
+        .length_field_length(4)
+        .length_adjustment(-4)
+        .num_skip(0)
+        .max_frame_length(opts.max_message_size.unwrap_or(usize::MAX))
+        .new_codec();
+    Ok((
+        FrameWriter::Tcp(w),
+        FrameReader::Tcp(FramedRead::new(r, codec)),
+    ))
+}
+
+pub(crate) async fn open_transport(
+    tag: ConnTag,
+    sub_urls: &[LiveHost],
+    opts: &ConnectOptions,
+    skip_tcp: bool,
+) -> Result<(FrameWriter, FrameReader), LiveConnectError> {
+    let mut hosts: Vec<&LiveHost> = sub_urls.iter().collect();
+    opts.host_order.apply(&mut hosts);
+
+    let mut candidates = Vec::new();
+    if opts.transport == Transport::TcpThenWss && !skip_tcp {
+        for host in &hosts {
+            candidates.push((true, format!("{}:{}", host.host, host.port)));
+        }
+    }
+    for host in &hosts {
+        let url = match opts.transport {
+            Transport::WsOnly => host.ws_url(),
+            Transport::WssOnly | Transport::TcpThenWss => host.wss_url(),
+        };
+        candidates.push((false, url));
+    }
+
+    let mut err = LiveConnectError::NoHost;
+    for (is_tcp, url) in candidates {
+        let r = if is_tcp {
+            open_tcp(&url, opts).await
+        } else {
+            open_ws(&url, opts).await
+        };
+        match r {
+            Ok(r) => {
+                info!("{tag} connect to {url}");
+                return Ok(r);
+            }
+            Err(e) => {
+                error!("{tag} connect to {url} error {:?}", e);
+                err = e;
+            }
+        }
+    }
+    Err(err)
+}
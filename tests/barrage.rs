@@ -0,0 +1,24 @@
+use bilili_rs::api::{APIClient, BarrageOptions, SendBarrageError};
+
+fn api_client() -> APIClient {
+    APIClient::from_cookie_str("DedeUserID=1; SESSDATA=a; bili_jct=b").unwrap()
+}
+
+// 房间号没有缓存，如果先查询真实房间号会走网络请求
+#[tokio::test]
+async fn invalid_mode_checked_before_request() {
+    let opts = BarrageOptions {
+        mode: 2,
+        ..Default::default()
+    };
+    let r = api_client().send_barrage_with(1234u64, "hello", opts).await;
+    assert!(matches!(r, Err(SendBarrageError::InvalidMode(2))), "{r:?}");
+}
+
+#[tokio::test]
+async fn empty_barrage_checked_before_request() {
+    let r = api_client()
+        .send_long_barrage(1234u64, "  ", BarrageOptions::default())
+        .await;
+    assert!(matches!(r, Err(SendBarrageError::Empty)), "{r:?}");
+}
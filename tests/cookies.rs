@@ -0,0 +1,35 @@
+//! 浏览器导出的 Netscape 格式 `cookies.txt`
+
+use bilili_rs::api::{parse_netscape_cookies, Session};
+
+const COOKIES_TXT: &str = "# Netscape HTTP Cookie File
+# This file was generated by a browser extension
+
+.bilibili.com	TRUE	/	FALSE	1900000000	DedeUserID	10001
+#HttpOnly_.bilibili.com	TRUE	/	TRUE	1900000000	SESSDATA	abc%2C123
+.bilibili.com	TRUE	/	FALSE	1900000000	bili_jct	0123456789abcdef
+.example.com	TRUE	/	FALSE	1900000000	SESSDATA	other
+bad line
+";
+
+#[test]
+fn parse_cookies_txt() {
+    let cookies = parse_netscape_cookies(COOKIES_TXT);
+    assert_eq!(
+        cookies,
+        [
+            "DedeUserID=10001; Domain=.bilibili.com; Path=/",
+            "SESSDATA=abc%2C123; Domain=.bilibili.com; Path=/; Secure",
+            "bili_jct=0123456789abcdef; Domain=.bilibili.com; Path=/",
+        ]
+    );
+}
+
+#[test]
+fn session_detects_cookies_txt() {
+    let session = Session::parse(COOKIES_TXT).unwrap();
+    assert_eq!(session.cookies.len(), 3);
+    let client = session.into_client().unwrap();
+    assert_eq!(client.token.uid, "10001");
+    assert_eq!(client.token.csrf, "0123456789abcdef");
+}
@@ -0,0 +1,143 @@
+use bilili_rs::live_ws::{GuardLevel, NotificationMsg, ServerLiveMessage};
+
+fn parse_danmu(raw: &str) -> bilili_rs::live_ws::message::notification_msg::DanmuMsg {
+    match serde_json::from_str::<NotificationMsg>(raw).unwrap() {
+        NotificationMsg::DANMU_MSG { info } => info,
+        msg => panic!("unexpected {:?}", msg),
+    }
+}
+
+// 以下弹幕按 DANMU_MSG 的字段布局手写，不是抓包数据
+
+// 戴着别的主播（uid 20002）的舰长勋章，在当前直播间没有大航海，info[7] 为 0
+const OTHER_ROOM_CAPTAIN: &str = r#"{"cmd":"DANMU_MSG","info":[[0,1,25,16777215,1700000000123,1700000000,0,"a1b2c3d4",0,0,0,"",0,"{}","{}",{"mode":0,"show_player_type":0,"extra":"{}"},{"activity_identity":"","activity_source":0,"not_show":0},0],"晚上好",[10001,"某舰长",0,0,0,10000,1,""],[21,"牌子","主播",5050,1725515,"",0,6809855,1725515,5414290,3,1,20002],[11,0,6406234,">50000",0],["",""],0,0,null,{"ts":1700000000,"ct":"ABCDEF01"},0,0,null,null,0,105,[1],null]}"#;
+
+// info[7] 不是数字，大航海等级只能从粉丝勋章得到
+const MEDAL_ONLY: &str = r#"{"cmd":"DANMU_MSG","info":[[0,1,25,16777215,1700000000124,1700000000,0,"a1b2c3d5",0,0,0,"",0,"{}","{}",{"mode":0,"show_player_type":0,"extra":"{}"},{"activity_identity":"","activity_source":0,"not_show":0},0],"晚上好",[10001,"某舰长",0,0,0,10000,1,""],[21,"牌子","主播",5050,1725515,"",0,6809855,1725515,5414290,3,1,20002],[11,0,6406234,">50000",0],["",""],0,{},null,{"ts":1700000000,"ct":"ABCDEF01"},0,0,null,null,0,105,[1],null]}"#;
+
+// info[7] 为大航海等级
+const GOVERNOR: &str = r#"{"cmd":"DANMU_MSG","info":[[0,1,25,16777215,1700000000456,1700000000,0,"e5f6a7b8",0,0,0,"",0,"{}","{}",{"mode":0,"show_player_type":0,"extra":"{}"},{"activity_identity":"","activity_source":0,"not_show":0},0],"来了",[10002,"某总督",0,0,0,10000,1,""],[30,"牌子","主播",5050,1725515,"",0,6809855,1725515,5414290,1,1,20002],[20,0,6406234,">50000",0],["",""],0,1,null,{"ts":1700000000,"ct":"ABCDEF02"},0,0,null,null,0,105,[1],null]}"#;
+
+// 没有粉丝勋章也没有大航海
+const NO_GUARD: &str = r#"{"cmd":"DANMU_MSG","info":[[0,1,25,16777215,1700000000789,1700000000,0,"c9d0e1f2",0,0,0,"",0,"{}","{}",{"mode":0,"show_player_type":0,"extra":"{}"},{"activity_identity":"","activity_source":0,"not_show":0},0],"路过",[10003,"路人",0,0,0,10000,1,""],[],[0,0,9868950,">50000",0],["",""],0,0,null,{"ts":1700000000,"ct":"ABCDEF03"},0,0,null,null,0,105,[1],null]}"#;
+
+#[test]
+fn other_room_medal_is_not_guard() {
+    let danmu = parse_danmu(OTHER_ROOM_CAPTAIN);
+    assert_eq!(danmu.uid, 10001);
+    assert_eq!(danmu.text, "晚上好");
+    assert_eq!(danmu.medal_lv, 21);
+    assert_eq!(danmu.medal_owner_uid, 20002);
+    assert_eq!(danmu.guard_level, None);
+    assert_eq!(danmu.guard_level_in(30003), None);
+    assert_eq!(danmu.guard_level_in(20002), None);
+}
+
+#[test]
+fn guard_level_from_medal() {
+    let danmu = parse_danmu(MEDAL_ONLY);
+    assert_eq!(danmu.guard_level, None);
+    assert_eq!(danmu.guard_level_in(20002), Some(GuardLevel::Captain));
+    assert_eq!(danmu.guard_level_in(30003), None);
+}
+
+#[test]
+fn medal_color() {
+    let danmu = parse_danmu(OTHER_ROOM_CAPTAIN);
+    assert_eq!(danmu.medal_color_start, 1725515);
+    assert_eq!(danmu.medal_color_end, 5414290);
+    assert_eq!(danmu.medal_color_border, 6809855);
+    assert!(danmu.is_lighted);
+
+    let danmu = parse_danmu(NO_GUARD);
+    assert_eq!(danmu.medal_color_start, 0);
+    assert!(!danmu.is_lighted);
+}
+
+#[test]
+fn guard_level_from_info() {
+    let danmu = parse_danmu(GOVERNOR);
+    assert_eq!(danmu.guard_level, Some(GuardLevel::Governor));
+}
+
+#[test]
+fn no_guard_level() {
+    let danmu = parse_danmu(NO_GUARD);
+    assert_eq!(danmu.uname, "路人");
+    assert_eq!(danmu.guard_level, None);
+    assert_eq!(danmu.timestamp, 1700000000789);
+}
+
+#[test]
+fn like_click() {
+    let raw = r#"{"cmd":"LIKE_INFO_V3_CLICK","data":{"show_area":0,"msg_type":6,"like_icon":"","uid":10005,"like_text":"为主播点赞了","fans_medal":{"target_id":20002,"medal_level":3,"medal_name":"牌子","guard_level":0,"anchor_roomid":1234},"uname":"点赞的人","uname_color":"","contribution_info":{"grade":0},"dmscore":20}}"#;
+    match serde_json::from_str::<NotificationMsg>(raw).unwrap() {
+        NotificationMsg::LIKE_INFO_V3_CLICK { data } => {
+            assert_eq!(data.uid, 10005);
+            assert_eq!(data.uname, "点赞的人");
+            assert_eq!(data.like_text, "为主播点赞了");
+            assert_eq!(data.fans_medal.unwrap().medal_level, 3);
+        }
+        msg => panic!("unexpected {:?}", msg),
+    }
+
+    let raw = r#"{"cmd":"LIKE_INFO_V3_UPDATE","data":{"click_count":12345}}"#;
+    match serde_json::from_str::<NotificationMsg>(raw).unwrap() {
+        NotificationMsg::LIKE_INFO_V3_UPDATE { data } => assert_eq!(data.click_count, 12345),
+        msg => panic!("unexpected {:?}", msg),
+    }
+}
+
+#[test]
+fn stream_end() {
+    let raw = r#"{"cmd":"STOP_LIVE_ROOM_LIST","data":{"room_id_list":[1017,21452505,22637261]}}"#;
+    match serde_json::from_str::<NotificationMsg>(raw).unwrap() {
+        NotificationMsg::STOP_LIVE_ROOM_LIST { data } => assert!(data.contains(21452505)),
+        msg => panic!("unexpected {:?}", msg),
+    }
+
+    let raw = r#"{"cmd":"PREPARING","roomid":"21452505"}"#;
+    let msg = ServerLiveMessage::Notification(serde_json::from_str(raw).unwrap());
+    assert!(msg.is_stream_end());
+    assert!(!ServerLiveMessage::ServerHeartBeat(1).is_stream_end());
+}
+
+#[test]
+fn unknown_cmd_keeps_raw() {
+    let raw = r#"{"cmd":"SOME_NEW_CMD","data":{"a":1}}"#;
+    let msg: NotificationMsg = serde_json::from_str(raw).unwrap();
+    let NotificationMsg::Unknown { cmd, raw: data } = &msg else {
+        panic!("expected Unknown, got {msg:?}");
+    };
+    assert_eq!(cmd, "SOME_NEW_CMD");
+    assert_eq!(data["data"]["a"], 1);
+    assert!(data.get("cmd").is_none());
+    let value = serde_json::to_value(&msg).unwrap();
+    assert_eq!(
+        value,
+        serde_json::from_str::<serde_json::Value>(raw).unwrap()
+    );
+}
+
+#[test]
+fn known_cmd_schema_drift_is_error() {
+    let raw = r#"{"cmd":"PREPARING","roomid":{"unexpected":true}}"#;
+    let err = serde_json::from_str::<NotificationMsg>(raw).unwrap_err();
+    assert!(err.to_string().contains("PREPARING"), "{err}");
+
+    let raw = r#"{"cmd":"PREPARING","roomid":"1234"}"#;
+    let msg: NotificationMsg = serde_json::from_str(raw).unwrap();
+    assert!(matches!(msg, NotificationMsg::PREPARING { .. }));
+}
+
+#[test]
+fn from_slice_matches_deserialize() {
+    let msg = NotificationMsg::from_slice(br#"{"cmd":"SOME_NEW_CMD","a":1}"#).unwrap();
+    assert!(matches!(msg, NotificationMsg::Unknown { ref cmd, .. } if cmd == "SOME_NEW_CMD"));
+
+    let err = NotificationMsg::from_slice(br#"{"cmd":"PREPARING","roomid":[]}"#).unwrap_err();
+    assert!(err.to_string().contains("PREPARING"), "{err}");
+
+    let msg = NotificationMsg::from_slice(br#"{"cmd":"PREPARING","roomid":"1"}"#).unwrap();
+    assert!(matches!(msg, NotificationMsg::PREPARING { .. }));
+}
@@ -0,0 +1,215 @@
+//! 用 `tests/fixtures` 里的数据帧测试 `decode_from_server` 和 `decode_frames`
+//!
+//! 这些帧按协议格式手工构造，不是抓包数据，包体里的 json 只保留了测试用到的字段。
+//!
+//! 每一帧由 16 字节的包头和包体组成，包头依次为：
+//! 包长度 u32、包头长度 u16、协议版本 u16、操作码 u32、序号 u32，均为大端序。
+//! 协议版本 0 为 json，1 为心跳/认证，2 为 zlib 压缩的多个包，3 为 brotli 压缩的多个包。
+
+use bilili_rs::live_ws::message::{decode_frames, decode_from_server, decode_iter, FrameBody};
+use bilili_rs::live_ws::{
+    ClientLiveMessage, GuardLevel, MsgDecodeError, NotificationMsg, ServerLiveMessage, WsLogin,
+};
+
+fn decode(
+    data: &[u8],
+) -> (
+    Result<(), MsgDecodeError>,
+    Vec<ServerLiveMessage>,
+    Vec<MsgDecodeError>,
+) {
+    let mut list = Vec::new();
+    let mut errors = Vec::new();
+    let r = decode_from_server(data.to_vec(), &mut list, &mut errors);
+    (r, list, errors)
+}
+
+fn assert_danmu(msg: &ServerLiveMessage) {
+    match msg {
+        ServerLiveMessage::Notification(NotificationMsg::DANMU_MSG { info }) => {
+            assert_eq!(info.uid, 10001);
+            assert_eq!(info.uname, "某舰长");
+            assert_eq!(info.text, "晚上好");
+            assert_eq!(info.guard_level, Some(GuardLevel::Captain));
+            assert_eq!(info.timestamp, 1700000000123);
+        }
+        msg => panic!("expect DANMU_MSG, got {:?}", msg),
+    }
+}
+
+fn assert_gift(msg: &ServerLiveMessage) {
+    match msg {
+        ServerLiveMessage::Notification(NotificationMsg::SEND_GIFT { data }) => {
+            assert_eq!(data.gift_id, 31036);
+            assert_eq!(data.gift_name, "小花花");
+            assert_eq!(data.num, 3);
+            assert_eq!(data.total_coin, 300);
+            assert_eq!(data.uid, 10004);
+        }
+        msg => panic!("expect SEND_GIFT, got {:?}", msg),
+    }
+}
+
+#[test]
+fn heartbeat_with_popularity() {
+    let (r, msgs, errors) = decode(include_bytes!("fixtures/heartbeat.bin"));
+    r.unwrap();
+    assert!(errors.is_empty());
+    assert!(matches!(
+        msgs.as_slice(),
+        [ServerLiveMessage::ServerHeartBeat(123456)]
+    ));
+}
+
+#[test]
+fn login_ack() {
+    let (r, msgs, errors) = decode(include_bytes!("fixtures/login_ack.bin"));
+    r.unwrap();
+    assert!(errors.is_empty());
+    assert!(matches!(msgs.as_slice(), [ServerLiveMessage::LoginAck]));
+}
+
+#[test]
+fn danmu_msg() {
+    let (r, msgs, errors) = decode(include_bytes!("fixtures/danmu_msg.bin"));
+    r.unwrap();
+    assert!(errors.is_empty());
+    assert_eq!(msgs.len(), 1);
+    assert_danmu(&msgs[0]);
+}
+
+#[test]
+fn send_gift() {
+    let (r, msgs, errors) = decode(include_bytes!("fixtures/send_gift.bin"));
+    r.unwrap();
+    assert!(errors.is_empty());
+    assert_eq!(msgs.len(), 1);
+    assert_gift(&msgs[0]);
+}
+
+#[test]
+fn zlib_batch() {
+    let (r, msgs, errors) = decode(include_bytes!("fixtures/zlib_batch.bin"));
+    r.unwrap();
+    assert!(errors.is_empty());
+    assert_eq!(msgs.len(), 3);
+    assert_danmu(&msgs[0]);
+    assert_gift(&msgs[1]);
+    assert_danmu(&msgs[2]);
+}
+
+#[test]
+fn truncated_header() {
+    let (r, msgs, _) = decode(include_bytes!("fixtures/truncated.bin"));
+    assert!(matches!(r, Err(MsgDecodeError::BadHeader)));
+    assert!(msgs.is_empty());
+}
+
+#[test]
+fn unknown_version() {
+    let (r, _, _) = decode(include_bytes!("fixtures/unknown_version.bin"));
+    assert!(matches!(
+        r,
+        Err(MsgDecodeError::UndefinedMsg {
+            pkg_v: 4,
+            pkg_type: 5
+        })
+    ));
+}
+
+#[test]
+fn bad_body_does_not_stop_frame() {
+    let (r, msgs, errors) = decode(include_bytes!("fixtures/bad_json.bin"));
+    r.unwrap();
+    assert!(matches!(
+        errors.as_slice(),
+        [MsgDecodeError::DecodeBodyError(_)]
+    ));
+    assert!(matches!(
+        msgs.as_slice(),
+        [ServerLiveMessage::ServerHeartBeat(7)]
+    ));
+}
+
+#[test]
+fn iter_zlib_batch() {
+    let msgs: Vec<_> = decode_iter(include_bytes!("fixtures/zlib_batch.bin").to_vec())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(msgs.len(), 3);
+    assert_gift(&msgs[1]);
+}
+
+#[test]
+fn iter_continues_after_bad_body() {
+    let mut iter = decode_iter(include_bytes!("fixtures/bad_json.bin").to_vec());
+    assert!(matches!(
+        iter.next(),
+        Some(Err(MsgDecodeError::DecodeBodyError(_)))
+    ));
+    assert!(matches!(
+        iter.next(),
+        Some(Ok(ServerLiveMessage::ServerHeartBeat(7)))
+    ));
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn frames_of_zlib_batch() {
+    let frames = decode_frames(include_bytes!("fixtures/zlib_batch.bin")).unwrap();
+    assert_eq!(frames.len(), 3);
+    for frame in &frames {
+        assert_eq!(frame.version, 0);
+        assert_eq!(frame.op, 5);
+    }
+    match &frames[1].body {
+        FrameBody::Message(msg) => assert_gift(msg),
+        body => panic!("expect message, got {:?}", body),
+    }
+}
+
+#[test]
+fn frames_keep_bad_body() {
+    let frames = decode_frames(include_bytes!("fixtures/bad_json.bin")).unwrap();
+    assert!(matches!(frames[0].body, FrameBody::Raw(_)));
+    assert!(matches!(
+        frames[1].body,
+        FrameBody::Message(ServerLiveMessage::ServerHeartBeat(7))
+    ));
+}
+
+#[test]
+fn login_packet_seq() {
+    let login = ClientLiveMessage::Login(WsLogin {
+        room_id: 1234,
+        uid: 0,
+        key: "key".to_string(),
+    });
+    let frames = decode_frames(&login.encode_with_seq(3)).unwrap();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].op, 7);
+    assert_eq!(frames[0].seq, 3);
+}
+
+#[test]
+fn packet_after_compressed_packet() {
+    let mut data = include_bytes!("fixtures/zlib_batch.bin").to_vec();
+    data.extend_from_slice(include_bytes!("fixtures/heartbeat.bin"));
+    let (r, msgs, errors) = decode(&data);
+    r.unwrap();
+    assert!(errors.is_empty());
+    assert_eq!(msgs.len(), 4);
+    assert!(matches!(
+        msgs[3],
+        ServerLiveMessage::ServerHeartBeat(123456)
+    ));
+}
+
+#[test]
+fn length_beyond_data_is_bad_header() {
+    let mut data = vec![0xff, 0xff, 0xff, 0xff, 0, 16, 0, 0, 0, 0, 0, 5, 0, 0, 0, 1];
+    data.extend_from_slice(b"{}");
+    let (r, msgs, _) = decode(&data);
+    assert!(matches!(r, Err(MsgDecodeError::BadHeader)));
+    assert!(msgs.is_empty());
+}
@@ -0,0 +1,17 @@
+use bilili_rs::api::GiftSendResult;
+
+#[test]
+fn gift_send_result_round_trip() {
+    let raw = r#"{"tid":"1700000000123","gift_list":[{"gift_id":32251,"gift_name":"小蛋糕","combo_id":"gift:combo_id:1","blind_gift":{"original_gift_id":32251,"original_gift_name":"心动盲盒","original_gift_price":15000,"gift_tip_price":16000,"gift_action":"爆出"}}]}"#;
+    let result: GiftSendResult = serde_json::from_str(raw).unwrap();
+    assert_eq!(result.tid, "1700000000123");
+    assert_eq!(result.combo_id.as_deref(), Some("gift:combo_id:1"));
+    assert_eq!(result.blind_gift.as_ref().unwrap().gift_name, "小蛋糕");
+
+    let json = serde_json::to_string(&result).unwrap();
+    let again: GiftSendResult = serde_json::from_str(&json).unwrap();
+    assert_eq!(again.raw, result.raw);
+    assert_eq!(again.tid, result.tid);
+    assert_eq!(again.combo_id, result.combo_id);
+    assert!(again.raw.get("raw").is_none());
+}
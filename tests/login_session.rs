@@ -0,0 +1,22 @@
+use bilili_rs::api::LoginSession;
+
+// 按 web_line 列表中单条记录的字段手写的样例，不是抓包数据
+#[test]
+fn login_session_fields() {
+    let raw = r#"{
+        "session_id": "a1b2c3",
+        "device_name": "Chrome on Windows",
+        "location": "上海",
+        "login_time": 1700000000,
+        "is_current": true
+    }"#;
+    let session: LoginSession = serde_json::from_str(raw).unwrap();
+    assert_eq!(session.session_id, "a1b2c3");
+    assert_eq!(session.device, "Chrome on Windows");
+    assert_eq!(session.location, "上海");
+    assert_eq!(session.last_active, 1700000000);
+    assert!(session.current);
+
+    let session: LoginSession = serde_json::from_str(r#"{"session_id":"x"}"#).unwrap();
+    assert!(!session.current);
+}
@@ -0,0 +1,32 @@
+use bilili_rs::live_ws::NotificationMsg;
+
+// 按 PK_BATTLE_PROCESS_NEW 的字段布局手写的样例，不是抓包数据
+const PROCESS_NEW: &str = r#"{"cmd":"PK_BATTLE_PROCESS_NEW","pk_id":326153972,"pk_status":201,"timestamp":1700000123,"data":{"battle_type":1,"init_info":{"room_id":21452505,"votes":1530,"best_uname":"某观众","vision_desc":0},"match_info":{"room_id":22637261,"votes":820,"best_uname":"","vision_desc":0},"trace_id":"1700000123000"}}"#;
+
+const END: &str = r#"{"cmd":"PK_BATTLE_END","pk_id":"326153972","pk_status":401,"timestamp":1700000300,"data":{"battle_type":1,"timer":10,"init_info":{"room_id":21452505,"votes":2030,"winner_type":2,"best_uname":"某观众"},"match_info":{"room_id":22637261,"votes":1200,"winner_type":-1,"best_uname":"另一个观众"}}}"#;
+
+#[test]
+fn pk_process() {
+    match serde_json::from_str::<NotificationMsg>(PROCESS_NEW).unwrap() {
+        NotificationMsg::PK_BATTLE_PROCESS_NEW { pk_id, data } => {
+            assert_eq!(pk_id, 326153972);
+            assert_eq!(data.init_info.room_id, 21452505);
+            assert_eq!(data.init_info.votes, 1530);
+            assert_eq!(data.init_info.best_uname, "某观众");
+            assert_eq!(data.match_info.room_id, 22637261);
+            assert_eq!(data.match_info.votes, 820);
+        }
+        msg => panic!("unexpected {:?}", msg),
+    }
+}
+
+#[test]
+fn pk_end_winner() {
+    match serde_json::from_str::<NotificationMsg>(END).unwrap() {
+        NotificationMsg::PK_BATTLE_END { pk_id, data } => {
+            assert_eq!(pk_id, 326153972);
+            assert_eq!(data.winner().map(|r| r.room_id), Some(21452505));
+        }
+        msg => panic!("unexpected {:?}", msg),
+    }
+}
@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use bilili_rs::live_ws::ReconnectPolicy;
+
+#[test]
+fn default_policy_steps_to_max() {
+    let policy = ReconnectPolicy::default();
+    assert_eq!(policy.delay(1), Duration::from_secs(10));
+    assert_eq!(policy.delay(20), Duration::from_secs(10));
+    assert_eq!(policy.delay(21), Duration::from_secs(300));
+    assert_eq!(policy.delay(100), Duration::from_secs(300));
+}
+
+#[test]
+fn exponential_policy_capped() {
+    let policy = ReconnectPolicy {
+        base: Duration::from_secs(1),
+        factor: 2.0,
+        max_after: 0,
+        ..Default::default()
+    };
+    assert_eq!(policy.delay(1), Duration::from_secs(1));
+    assert_eq!(policy.delay(4), Duration::from_secs(8));
+    assert_eq!(policy.delay(20), Duration::from_secs(300));
+}
@@ -0,0 +1,14 @@
+use bilili_rs::api::RoomId;
+
+#[test]
+fn room_id_from_number_and_str() {
+    assert_eq!(RoomId::from(1234u64), RoomId::Unknown(1234));
+    assert_eq!(RoomId::from(1234u32), RoomId::Unknown(1234));
+    assert_eq!(
+        "22603245".parse::<RoomId>().unwrap(),
+        RoomId::Unknown(22603245)
+    );
+    assert_eq!(" 5 ".parse::<RoomId>().unwrap(), RoomId::Unknown(5));
+    assert!("abc".parse::<RoomId>().is_err());
+    assert!("-1".parse::<RoomId>().is_err());
+}
@@ -0,0 +1,92 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use bilili_rs::api::{APIClient, LiveHost};
+use bilili_rs::live_ws::{
+    connect_with, ConnectOptions, LiveConnectError, ReconnectPolicy, Transport,
+};
+use tokio::net::TcpListener;
+
+const ROOM_ID: u64 = 1;
+
+fn api_client() -> Arc<APIClient> {
+    let client = APIClient::from_cookie_str("DedeUserID=1; SESSDATA=a; bili_jct=b").unwrap();
+    client.set_real_room_id(ROOM_ID, ROOM_ID);
+    Arc::new(client)
+}
+
+fn options(port: u16, wss_port: u16) -> ConnectOptions {
+    ConnectOptions {
+        max_retry: 3,
+        transport: Transport::TcpThenWss,
+        token: Some("token".to_string()),
+        hosts: Some(vec![LiveHost {
+            host: "127.0.0.1".to_string(),
+            port: port as u32,
+            ws_port: 0,
+            wss_port: wss_port as u32,
+        }]),
+        reconnect: ReconnectPolicy {
+            base: Duration::from_millis(10),
+            max_after: 0,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// 接受连接后立即关闭，返回端口和收到的连接数
+async fn closing_listener() -> (u16, Arc<AtomicU32>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let count = Arc::new(AtomicU32::new(0));
+    let counter = count.clone();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            drop(stream);
+        }
+    });
+    (port, count)
+}
+
+async fn refused_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+#[tokio::test]
+async fn refused_tcp_falls_back_to_wss_and_retries() {
+    let tcp_port = refused_port().await;
+    let (wss_port, wss_count) = closing_listener().await;
+
+    let mut stream = connect_with(api_client(), ROOM_ID, options(tcp_port, wss_port));
+    let r = tokio::time::timeout(Duration::from_secs(10), async move {
+        while stream.rx.recv().await.is_some() {}
+        stream.finished().await
+    })
+    .await
+    .unwrap();
+
+    assert!(matches!(r, Err(LiveConnectError::RetryTimeout)), "{r:?}");
+    assert_eq!(wss_count.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn tcp_closed_before_login_reconnects_with_wss() {
+    let (tcp_port, tcp_count) = closing_listener().await;
+    let (wss_port, wss_count) = closing_listener().await;
+
+    let mut stream = connect_with(api_client(), ROOM_ID, options(tcp_port, wss_port));
+    let r = tokio::time::timeout(Duration::from_secs(10), async move {
+        while stream.rx.recv().await.is_some() {}
+        stream.finished().await
+    })
+    .await
+    .unwrap();
+
+    assert!(matches!(r, Err(LiveConnectError::RetryTimeout)), "{r:?}");
+    assert_eq!(tcp_count.load(Ordering::SeqCst), 1);
+    assert_eq!(wss_count.load(Ordering::SeqCst), 2);
+}